@@ -18,39 +18,6 @@ app.get('/generation', (req, res) => {
   res.sendFile(path.join(__dirname, 'data_generation.html'));
 });
 
-let text_to_factor_index = (text) =>{
-  switch (text) {
-    case "custom formula bugonly more newest change":return 25
-    case "custom formula bugonly":return 18
-    case "custom formula bug2 more newest change":return 23
-    case "custom formula bug2":return 16
-    case "custom formula bug1 more newest change":return 22
-    case "custom formula bug1":return 15
-    case "custom formula more newest change":return 19
-    case "custom formula":return 12
-    case "newest change":return 3
-    case "custom formula freq1 more newest change":return 20
-    case "custom formula freq1":return 13
-    case "custom formula freqonly more newest change":return 24
-    case "custom formula freqonly":return 17
-    case "custom formula freq2 more newest change":return 21
-    case "custom formula freq2":return 14
-    case "frequency aged by commit ages * newest change":return 10
-    case "frequency aged by commit ages":return 4
-    case "frequency aged by most recent newest file change":return 6
-    case "fixed bugs aged by commit ages * newest change ":return 11
-    case "fixed bugs aged by most recent newest file change":return 7
-    case "fixed bugs aged by commit ages":return 5
-    case "frequency":return 0
-    case "fixed bugs":return 1
-    case "fixed bugs aged by most recent oldest file change":return 9
-    case "frequency aged by most recent oldest file change":return 8
-    case "oldest change":return 2
-    default:
-      return 25
-
-  }
-}
 //clones adress to temporary repo, returns repo path for later deletion(or not)
 let clone_adress = (adressUrl) => {
     // Create a temporary directory to clone the repository int
@@ -59,7 +26,7 @@ let clone_adress = (adressUrl) => {
     fs.rmSync("./downloaded_repositories/", { recursive: true, force: true });
     console.log("start cloning")
     // Clone the repository
-    childprocess.execSync(`git clone -c core.longpaths=true ${adressUrl} ${repoDir}`, [], {shell:false});
+    childprocess.execFileSync("git", ["clone", "-c", "core.longpaths=true", "--", adressUrl, repoDir]);
 
     return repoDir
 }
@@ -72,6 +39,13 @@ app.get('/full_backend_generation', (req, res) => {
   //works
   console.log("BACKEND COMMANDO " + req.url)
 
+  //factors are picked by slug (or number), "target/release/gitdiffjson factors" lists them
+  let factor = req.query.factor || "custom_bugonly_newest"
+  if (!/^[a-z0-9_]+$/.test(factor)){
+    res.status(400).send("factor must be a factor slug or number")
+    return
+  }
+
   let bugfix_regex_list = []
 
   if(req.query.bugfix_regex){
@@ -100,23 +74,20 @@ app.get('/full_backend_generation', (req, res) => {
   }
 
 
-  //run rust parsing on repo path
-  factor = text_to_factor_index(req.query.factor)
-  let path_command = '"' + compiled_rust + '" "repo" "' + path + '"'
-
-  console.log("command--->  " + path_command)
-  let _child1 = childprocess.execSync( path_command, [], {shell:false})
-//fix clone repo fix exe text inline TODO:
-  let d3_generation_command = '"' + compiled_rust + '" "d3" "generatedJson.json" "full" "files" "' + text_to_factor_index(factor) + '" "100"'
-  console.log("command--->  " + d3_generation_command)
-  let _child2 = childprocess.execSync(d3_generation_command, [], {shell:false})
+  //run rust parsing on repo path, the args are passed without a shell so the query can not add commands
+  let path_args = ["repo", "--", path]
+  console.log("command--->  " + compiled_rust + " " + path_args.join(" "))
+  let _child1 = childprocess.execFileSync(compiled_rust, path_args)
+  let d3_generation_args = ["d3", "--output", "full", "--view", "files", "--factor", factor, "--items", "100"]
+  console.log("command--->  " + compiled_rust + " " + d3_generation_args.join(" "))
+  let _child2 = childprocess.execFileSync(compiled_rust, d3_generation_args)
 
   if(req.query.is_remote === "true"){
     fs.rmSync("./downloaded_repositories/", { recursive: true, force: true });
     console.log("deleted local repo")
   }
   //let child = childprocess.exec("cd /dir > your_file.txt")
-  //target/release/gitdiffjson.exe "d3" "generatedJson.json" "full" "files" "26" "100"
+  //target/release/gitdiffjson.exe d3 --output "full" --view files --factor "26" --items 100
   console.log("finished BACKEND COMMANDO " + req.url)
 });
 
@@ -0,0 +1,417 @@
+use crate::factors::{slugify, Factor};
+use crate::report::{csv_field, MultiAnalysisReport};
+use crate::stats::{compare_factors, get_statistic_values, mean, median, variance, FactorStatistics};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+//How every factor did in one repository, read from raw data or from an earlier multi_analysis result
+pub struct RepoResult {
+    pub repo: String,
+    pub factors: Vec<FactorScore>,
+}
+
+pub struct FactorScore {
+    pub slug: String,
+    pub name: String,
+    //1 is the best, by average deviation
+    pub rank: usize,
+    pub deviation: f64,
+    pub auc: f64,
+    pub popt: f64,
+}
+
+//The parts of a json multi_analysis result that are aggregated, NaN values are null in it
+#[derive(Deserialize)]
+struct ReportSummary {
+    factors: Vec<FactorSummary>,
+}
+
+#[derive(Deserialize)]
+struct FactorSummary {
+    slug: String,
+    name: String,
+    rank: usize,
+    average_deviation: Option<f64>,
+    average_metrics: MetricsSummary,
+}
+
+#[derive(Deserialize)]
+struct MetricsSummary {
+    auc: Option<f64>,
+    popt: Option<f64>,
+}
+
+//Every factor over all the repositories, what "aggregate" writes
+#[derive(Serialize)]
+pub struct AggregateReport {
+    pub repos: Vec<String>,
+    //factor slugs, lowest mean rank first
+    pub ranking: Vec<String>,
+    pub factors: Vec<AggregateFactor>,
+}
+
+#[derive(Serialize)]
+pub struct AggregateFactor {
+    pub slug: String,
+    pub name: String,
+    pub mean_rank: f64,
+    pub mean_deviation: f64,
+    pub median_deviation: f64,
+    //sample variance over the repositories
+    pub deviation_variance: f64,
+    pub mean_auc: f64,
+    pub mean_popt: f64,
+    //significance of its place in the ranking, with the repositories as observations
+    pub statistics: FactorStatistics,
+    pub per_repo: Vec<RepoRank>,
+}
+
+#[derive(Serialize)]
+pub struct RepoRank {
+    pub repo: String,
+    pub rank: usize,
+    pub deviation: f64,
+}
+
+//"results/angular__macro_analysis.json" -> "angular", "angular.json" -> "angular"
+fn get_repo_name(path: &str) -> String {
+    let stem = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or(path);
+    stem.strip_suffix("__macro_analysis").unwrap_or(stem).to_string()
+}
+
+//The repository names of paths. Names that more than one path has get the folder in front, "a/vue.json" and
+//"b/vue__macro_analysis.txt" are "a/vue" and "b/vue". Err is a message for the user when that is not enough (the same file twice)
+pub fn get_repo_names(paths: &[String]) -> Result<Vec<String>, String> {
+    let names: Vec<String> = paths.iter().map(|path| get_repo_name(path)).collect();
+    let names: Vec<String> = paths
+        .iter()
+        .zip(&names)
+        .map(|(path, name)| match names.iter().filter(|other| *other == name).count() {
+            1 => name.to_owned(),
+            _ => Path::new(path).with_file_name(name).to_string_lossy().to_string(),
+        })
+        .collect();
+    for (i, name) in names.iter().enumerate() {
+        if let Some(j) = names[..i].iter().position(|other| other == name) {
+            return Err(format!("{} and {} are both the repository {}, give every result once", paths[j], paths[i], name));
+        }
+    }
+    Ok(names)
+}
+
+impl RepoResult {
+    pub fn from_report(repo: &str, report: &MultiAnalysisReport) -> RepoResult {
+        RepoResult {
+            repo: repo.to_string(),
+            factors: report
+                .factors
+                .iter()
+                .map(|factor| FactorScore {
+                    slug: factor.slug.to_owned(),
+                    name: factor.name.to_owned(),
+                    rank: factor.rank,
+                    deviation: factor.average_deviation,
+                    auc: factor.average_metrics.auc,
+                    popt: factor.average_metrics.popt,
+                })
+                .collect(),
+        }
+    }
+}
+
+//Reads a multi_analysis result, "__macro_analysis.txt" or ".json", of the repository repo. None when path is something else (raw data)
+pub fn read_result_file(path: &str, repo: &str, factors: &[Factor]) -> Option<RepoResult> {
+    let file_string = fs::read_to_string(path).unwrap();
+    if path.ends_with(".txt") {
+        return Some(parse_text_result(repo, &file_string, factors));
+    }
+    let value: serde_json::Value = serde_json::from_str(&file_string).unwrap();
+    value.get("ranking")?;
+    let summary: ReportSummary = serde_json::from_value(value).unwrap();
+    Some(RepoResult {
+        repo: repo.to_string(),
+        factors: summary
+            .factors
+            .into_iter()
+            .map(|factor| FactorScore {
+                slug: factor.slug,
+                name: factor.name,
+                rank: factor.rank,
+                deviation: factor.average_deviation.unwrap_or(f64::NAN),
+                auc: factor.average_metrics.auc.unwrap_or(f64::NAN),
+                popt: factor.average_metrics.popt.unwrap_or(f64::NAN),
+            })
+            .collect(),
+    })
+}
+
+//Text results start with "<factor name> > avg deviation = <number>" lines, best first.
+//Newer ones also have "<factor name> > auc = <number>, ..., popt = <number>, ..." lines. Names are matched to the current factors to get their slugs.
+fn parse_text_result(repo: &str, text: &str, factors: &[Factor]) -> RepoResult {
+    //(slug, name) of the current factor with that name, older results can have other spacing in the names
+    let get_factor = |name: &str| {
+        factors
+            .iter()
+            .find(|factor| slugify(&factor.name) == slugify(name))
+            .map(|factor| (factor.slug.to_owned(), factor.name.to_owned()))
+            .unwrap_or_else(|| (slugify(name), name.trim().to_string()))
+    };
+    let get_value = |metrics: &str, key: &str| {
+        metrics
+            .split(", ")
+            .find_map(|part| part.strip_prefix(key))
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .unwrap_or(f64::NAN)
+    };
+    let mut scores: Vec<FactorScore> = vec![];
+    for line in text.lines() {
+        let (name, value) = match line.split_once(" > ") {
+            Some(pair) => pair,
+            None => continue,
+        };
+        if let Some(deviation) = value.strip_prefix("avg deviation = ") {
+            let (slug, name) = get_factor(name);
+            scores.push(FactorScore {
+                slug,
+                name,
+                rank: scores.len() + 1,
+                deviation: deviation.trim().parse::<f64>().unwrap_or(f64::NAN),
+                auc: f64::NAN,
+                popt: f64::NAN,
+            });
+        } else if value.starts_with("auc = ") {
+            let (slug, _) = get_factor(name);
+            if let Some(score) = scores.iter_mut().find(|score| score.slug == slug) {
+                score.auc = get_value(value, "auc = ");
+                score.popt = get_value(value, "popt = ");
+            }
+        }
+    }
+    RepoResult {
+        repo: repo.to_string(),
+        factors: scores,
+    }
+}
+
+//Combines the factors of all repositories by slug, a factor missing in some repositories is aggregated over the ones that have it
+pub fn aggregate(results: &[RepoResult]) -> AggregateReport {
+    let mut slugs: Vec<(String, String)> = vec![];
+    for result in results {
+        for factor in &result.factors {
+            if !slugs.iter().any(|(slug, _)| *slug == factor.slug) {
+                slugs.push((factor.slug.to_owned(), factor.name.to_owned()));
+            }
+        }
+    }
+    //every repository is an observation for the significance tests, NaN where the factor is missing
+    let get_observations = |slug: &str, value: &dyn Fn(&FactorScore) -> f64| -> Vec<f64> {
+        results
+            .iter()
+            .map(|result| {
+                result
+                    .factors
+                    .iter()
+                    .find(|factor| factor.slug == slug)
+                    .map(value)
+                    .unwrap_or(f64::NAN)
+            })
+            .collect()
+    };
+    let deviations: Vec<Vec<f64>> = slugs.iter().map(|(slug, _)| get_observations(slug, &|score| score.deviation)).collect();
+    let aucs: Vec<Vec<f64>> = slugs.iter().map(|(slug, _)| get_observations(slug, &|score| score.auc)).collect();
+    let mean_ranks: Vec<f64> = slugs
+        .iter()
+        .map(|(slug, _)| mean(&get_observations(slug, &|score| score.rank as f64)))
+        .collect();
+    let mut ranking: Vec<usize> = (0..slugs.len()).collect();
+    ranking.sort_by(|a, b| {
+        mean_ranks[*a]
+            .total_cmp(&mean_ranks[*b])
+            .then(mean(&deviations[*b]).total_cmp(&mean(&deviations[*a])))
+    });
+    let statistics = compare_factors(&deviations, &aucs, &ranking);
+
+    let factors: Vec<AggregateFactor> = ranking
+        .iter()
+        .map(|i| {
+            let (slug, name) = &slugs[*i];
+            let scores: Vec<(&String, &FactorScore)> = results
+                .iter()
+                .filter_map(|result| {
+                    result
+                        .factors
+                        .iter()
+                        .find(|factor| factor.slug == *slug)
+                        .map(|factor| (&result.repo, factor))
+                })
+                .collect();
+            AggregateFactor {
+                slug: slug.to_owned(),
+                name: name.to_owned(),
+                mean_rank: mean_ranks[*i],
+                mean_deviation: mean(&deviations[*i]),
+                median_deviation: median(&deviations[*i]),
+                deviation_variance: variance(&deviations[*i]),
+                mean_auc: mean(&aucs[*i]),
+                mean_popt: mean(&scores.iter().map(|(_, score)| score.popt).collect::<Vec<f64>>()),
+                statistics: statistics[*i].clone(),
+                per_repo: scores
+                    .iter()
+                    .map(|(repo, score)| RepoRank {
+                        repo: repo.to_string(),
+                        rank: score.rank,
+                        deviation: score.deviation,
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    AggregateReport {
+        repos: results.iter().map(|result| result.repo.to_owned()).collect(),
+        ranking: factors.iter().map(|factor| factor.slug.to_owned()).collect(),
+        factors,
+    }
+}
+
+impl AggregateReport {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "repos: {}", self.repos.join(", "));
+        for factor in &self.factors {
+            let ranks: Vec<String> = factor
+                .per_repo
+                .iter()
+                .map(|repo_rank| format!("{}: {}", repo_rank.repo, repo_rank.rank))
+                .collect();
+            let _ = writeln!(
+                text,
+                "{} > mean rank = {:.2}, mean deviation = {}, median deviation = {}, deviation variance = {}, mean auc = {:.4}, mean popt = {:.4}, ranks = [{}]",
+                factor.name,
+                factor.mean_rank,
+                factor.mean_deviation,
+                factor.median_deviation,
+                factor.deviation_variance,
+                factor.mean_auc,
+                factor.mean_popt,
+                ranks.join(", ")
+            );
+        }
+        let _ = writeln!(text, "\nsignificance over the repositories (Scott-Knott tiers, bootstrap intervals, Wilcoxon signed-rank p-values)");
+        for factor in &self.factors {
+            let _ = writeln!(text, "{} > {}", factor.name, factor.statistics);
+        }
+        text
+    }
+
+    //One row per factor with a rank column per repository, empty when the factor is missing in it
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "factor,name,aggregate_rank,mean_rank,mean_deviation,median_deviation,deviation_variance,mean_auc,mean_popt,tier,deviation_ci_low,deviation_ci_high,auc_ci_low,auc_ci_high,p_vs_next,p_vs_best",
+        );
+        for repo in &self.repos {
+            let _ = write!(csv, ",{}", csv_field(&format!("rank_{}", repo)));
+        }
+        csv.push('\n');
+        for (i, factor) in self.factors.iter().enumerate() {
+            let _ = write!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                csv_field(&factor.slug),
+                csv_field(&factor.name),
+                i + 1,
+                factor.mean_rank,
+                factor.mean_deviation,
+                factor.median_deviation,
+                factor.deviation_variance,
+                factor.mean_auc,
+                factor.mean_popt
+            );
+            for (_, value) in get_statistic_values(&factor.statistics) {
+                let _ = write!(csv, ",{}", value);
+            }
+            for repo in &self.repos {
+                match factor.per_repo.iter().find(|repo_rank| repo_rank.repo == *repo) {
+                    Some(repo_rank) => {
+                        let _ = write!(csv, ",{}", repo_rank.rank);
+                    }
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //(slug, rank, deviation) of every factor in one repository
+    fn get_result(repo: &str, scores: &[(&str, usize, f64)]) -> RepoResult {
+        RepoResult {
+            repo: repo.to_string(),
+            factors: scores
+                .iter()
+                .map(|(slug, rank, deviation)| FactorScore {
+                    slug: slug.to_string(),
+                    name: format!("{}, the factor", slug),
+                    rank: *rank,
+                    deviation: *deviation,
+                    auc: f64::NAN,
+                    popt: f64::NAN,
+                })
+                .collect(),
+        }
+    }
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn factors_are_ranked_by_mean_rank() {
+        let report = aggregate(&[
+            get_result("angular", &[("x", 1, 10.0), ("y", 2, 5.0), ("z", 3, 1.0)]),
+            //z is missing here
+            get_result("vue", &[("y", 1, 8.0), ("x", 2, 6.0)]),
+            get_result("react", &[("x", 1, 9.0), ("y", 2, 4.0), ("z", 3, 2.0)]),
+        ]);
+        assert_eq!(report.repos, ["angular", "vue", "react"]);
+        assert_eq!(report.ranking, ["x", "y", "z"]);
+        let x = &report.factors[0];
+        assert_close(x.mean_rank, 4.0 / 3.0);
+        assert_close(x.mean_deviation, 25.0 / 3.0);
+        assert_close(x.median_deviation, 9.0);
+        assert_close(x.deviation_variance, 13.0 / 3.0);
+        assert_eq!(x.per_repo.iter().map(|repo_rank| repo_rank.rank).collect::<Vec<_>>(), [1, 2, 1]);
+        let z = &report.factors[2];
+        assert_close(z.mean_rank, 3.0);
+        assert_eq!(z.per_repo.iter().map(|repo_rank| repo_rank.repo.as_str()).collect::<Vec<_>>(), ["angular", "react"]);
+
+        //a tie in mean rank goes to the higher mean deviation
+        let report = aggregate(&[get_result("a", &[("x", 1, 3.0), ("y", 2, 4.0)]), get_result("b", &[("y", 1, 6.0), ("x", 2, 5.0)])]);
+        assert_eq!(report.ranking, ["y", "x"]);
+
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().ends_with(",p_vs_best,rank_a,rank_b"));
+        let y = lines.next().unwrap();
+        assert!(y.starts_with("y,\"y, the factor\",1,1.5,5,5,2,") && y.ends_with(",2,1"), "{}", y);
+        assert!(lines.next().unwrap().ends_with(",1,2"));
+    }
+
+    #[test]
+    fn repositories_with_the_same_name() {
+        let paths = |paths: &[&str]| paths.iter().map(|path| path.to_string()).collect::<Vec<String>>();
+        assert_eq!(get_repo_names(&paths(&["results/angular__macro_analysis.json", "vue.json"])).unwrap(), ["angular", "vue"]);
+        assert_eq!(
+            get_repo_names(&paths(&["a/vue.json", "b/vue__macro_analysis.txt", "react.json"])).unwrap(),
+            ["a/vue", "b/vue", "react"]
+        );
+        let error = get_repo_names(&paths(&["a/vue.json", "a/vue__macro_analysis.txt"])).unwrap_err();
+        assert!(error.contains("both the repository a/vue"), "{}", error);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::raw_data::CommitRecord;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+
+//Decides which commits are bugfixes, the bugs the factors are measured against
+pub trait BugfixClassifier {
+    fn is_bugfix(&self, sha: &str, commit: &CommitRecord) -> bool;
+}
+
+//A commit is a bugfix when the first line of its message matches any of the regexes ("regex_recognized_bugfixes.json" or the defaults)
+pub struct RegexClassifier {
+    regexes: Vec<Regex>,
+}
+
+impl RegexClassifier {
+    pub fn new(regexes: Vec<Regex>) -> RegexClassifier {
+        RegexClassifier { regexes }
+    }
+}
+
+impl BugfixClassifier for RegexClassifier {
+    fn is_bugfix(&self, _sha: &str, commit: &CommitRecord) -> bool {
+        self.regexes.iter().any(|regex| regex.is_match(commit.get_summary()))
+    }
+}
+
+//The header of a conventional commit message, "fix(parser)!: handle empty input"
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+}
+
+pub fn parse_conventional_commit(message: &str) -> Option<ConventionalCommit> {
+    let header_regex = Regex::new(r"^(?P<kind>[a-zA-Z]+)(\((?P<scope>[^()]*)\))?!?: ").unwrap();
+    let captures = header_regex.captures(message.lines().next()?.trim_start())?;
+    Some(ConventionalCommit {
+        kind: captures["kind"].to_lowercase(),
+        scope: captures.name("scope").map(|scope| scope.as_str().trim().to_lowercase()),
+    })
+}
+
+//A commit is a bugfix when the first line of its message is a conventional commit of one of the types ("fix" by default),
+//and when scopes are given, of one of the scopes. Other messages are not bugfixes.
+pub struct ConventionalCommitClassifier {
+    types: Vec<String>,
+    scopes: Vec<String>,
+}
+
+impl ConventionalCommitClassifier {
+    pub fn new(types: &[String], scopes: &[String]) -> ConventionalCommitClassifier {
+        ConventionalCommitClassifier {
+            types: types.iter().map(|kind| kind.to_lowercase()).collect(),
+            scopes: scopes.iter().map(|scope| scope.to_lowercase()).collect(),
+        }
+    }
+}
+
+impl BugfixClassifier for ConventionalCommitClassifier {
+    fn is_bugfix(&self, _sha: &str, commit: &CommitRecord) -> bool {
+        match parse_conventional_commit(&commit.message) {
+            Some(header) => {
+                self.types.contains(&header.kind)
+                    && (self.scopes.is_empty() || header.scope.is_some_and(|scope| self.scopes.contains(&scope)))
+            }
+            None => false,
+        }
+    }
+}
+
+//A commit is a bugfix when its message (body and footer included) references an issue that has a bug type in an issue tracker export.
+//Keys are matched without case, "LINE-123" and "line-123" are the same issue, "#123" matches the key "123".
+pub struct IssueTrackerClassifier {
+    bug_keys: HashSet<String>,
+    key_regex: Regex,
+}
+
+//The json exports that can be read, a list of {"key", "type"} or the "issues" of a Jira search
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IssueExport {
+    Jira { issues: Vec<JiraIssue> },
+    List(Vec<IssueEntry>),
+}
+
+#[derive(Deserialize)]
+struct IssueEntry {
+    key: String,
+    #[serde(rename = "type")]
+    issue_type: String,
+}
+
+#[derive(Deserialize)]
+struct JiraIssue {
+    key: String,
+    fields: JiraFields,
+}
+
+#[derive(Deserialize)]
+struct JiraFields {
+    issuetype: JiraIssueType,
+}
+
+#[derive(Deserialize)]
+struct JiraIssueType {
+    name: String,
+}
+
+fn normalize_issue_key(key: &str) -> String {
+    key.trim().trim_start_matches('#').to_uppercase()
+}
+
+//Splits a csv line, fields can be quoted and quotes in them doubled
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+impl IssueTrackerClassifier {
+    //Reads the (key, type) pairs from a .json or .csv export and keeps the keys with one of the bug_types (not case sensitive).
+    //The csv needs a header with a "key" or "issue key" column and a "type" or "issue type" column.
+    pub fn from_file(path: &str, bug_types: &[String]) -> Result<IssueTrackerClassifier, String> {
+        let file_string = fs::read_to_string(path).map_err(|err| format!("can not read the issues {}: {}", path, err))?;
+        let issues: Vec<(String, String)> = if path.to_lowercase().ends_with(".csv") {
+            let mut lines = file_string.lines().filter(|line| !line.trim().is_empty());
+            let header: Vec<String> = split_csv_line(lines.next().unwrap_or_default())
+                .iter()
+                .map(|column| column.trim().to_lowercase())
+                .collect();
+            let find_column = |names: [&str; 2]| {
+                header
+                    .iter()
+                    .position(|column| names.contains(&column.as_str()))
+                    .ok_or_else(|| format!("{} needs a \"{}\" column", path, names[0]))
+            };
+            let key_column = find_column(["key", "issue key"])?;
+            let type_column = find_column(["type", "issue type"])?;
+            lines
+                .map(split_csv_line)
+                .filter_map(|fields| Some((fields.get(key_column)?.to_owned(), fields.get(type_column)?.to_owned())))
+                .collect()
+        } else {
+            match serde_json::from_str(&file_string).map_err(|err| format!("invalid issues {}: {}", path, err))? {
+                IssueExport::Jira { issues } => issues.into_iter().map(|issue| (issue.key, issue.fields.issuetype.name)).collect(),
+                IssueExport::List(issues) => issues.into_iter().map(|issue| (issue.key, issue.issue_type)).collect(),
+            }
+        };
+        let bug_types: Vec<String> = bug_types.iter().map(|bug_type| bug_type.trim().to_lowercase()).collect();
+        let bug_keys: HashSet<String> = issues
+            .iter()
+            .filter(|(_, issue_type)| bug_types.contains(&issue_type.trim().to_lowercase()))
+            .map(|(key, _)| normalize_issue_key(key))
+            .collect();
+        eprintln!("{} of {} issues in {} are bugs", bug_keys.len(), issues.len(), path);
+        Ok(IssueTrackerClassifier {
+            bug_keys,
+            key_regex: Regex::new(r"(?i)\b[a-z][a-z0-9_]*-[0-9]+\b|#[0-9]+\b").unwrap(),
+        })
+    }
+}
+
+impl BugfixClassifier for IssueTrackerClassifier {
+    fn is_bugfix(&self, _sha: &str, commit: &CommitRecord) -> bool {
+        self.key_regex
+            .find_iter(&commit.message)
+            .any(|key| self.bug_keys.contains(&normalize_issue_key(key.as_str())))
+    }
+}
+
+//A commit is a bugfix when any of the classifiers says so
+pub struct AnyClassifier {
+    classifiers: Vec<Box<dyn BugfixClassifier>>,
+}
+
+impl BugfixClassifier for AnyClassifier {
+    fn is_bugfix(&self, sha: &str, commit: &CommitRecord) -> bool {
+        self.classifiers.iter().any(|classifier| classifier.is_bugfix(sha, commit))
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+//Parses "bugfix=regex,conventional,issues" (the classifiers to combine, "regex" by default and "regex,issues" when "issues=" is given),
+//"fix_types=fix,perf" and "fix_scopes=api,db" for the conventional commits, "issues=<export.json|csv>" and "bug_types=bug,defect"
+//for the issue tracker export. recognized_bugfix_indicators are the regexes of "regex", other args are ignored.
+//Err is a message for the user, the args come from the flags and the config file
+pub fn parse_bugfix_classifier(args: &[String], recognized_bugfix_indicators: &[Regex]) -> Result<AnyClassifier, String> {
+    let mut kinds: Option<Vec<String>> = None;
+    let mut fix_types = vec!["fix".to_string()];
+    let mut fix_scopes = vec![];
+    let mut issues_path: Option<String> = None;
+    let mut bug_types = vec!["bug".to_string()];
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("bugfix", value)) => kinds = Some(parse_list(value)),
+            Some(("fix_types", value)) => fix_types = parse_list(value),
+            Some(("fix_scopes", value)) => fix_scopes = parse_list(value),
+            Some(("issues", value)) => issues_path = Some(value.to_string()),
+            Some(("bug_types", value)) => bug_types = parse_list(value),
+            _ => {}
+        }
+    }
+    let kinds = kinds.unwrap_or_else(|| match issues_path {
+        Some(_) => vec!["regex".to_string(), "issues".to_string()],
+        None => vec!["regex".to_string()],
+    });
+    let classifiers: Vec<Box<dyn BugfixClassifier>> = kinds
+        .iter()
+        .map(|kind| -> Result<Box<dyn BugfixClassifier>, String> {
+            match kind.as_str() {
+                "regex" => Ok(Box::new(RegexClassifier::new(recognized_bugfix_indicators.to_vec()))),
+                "conventional" => Ok(Box::new(ConventionalCommitClassifier::new(&fix_types, &fix_scopes))),
+                "issues" => match &issues_path {
+                    Some(path) => Ok(Box::new(IssueTrackerClassifier::from_file(path, &bug_types)?)),
+                    None => Err("the issues classifier needs --issues <issue tracker export .json or .csv> (issues in the config)".to_string()),
+                },
+                _ => Err(format!("unknown bugfix classifier {}, use regex, conventional or issues", kind)),
+            }
+        })
+        .collect::<Result<_, String>>()?;
+    if classifiers.is_empty() {
+        return Err("--bugfix needs at least one of regex, conventional or issues".to_string());
+    }
+    Ok(AnyClassifier { classifiers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_commit_record(message: &str) -> CommitRecord {
+        CommitRecord {
+            message: message.to_string(),
+            age: 0,
+            author_name: None,
+            author_email: None,
+            author_time: None,
+            committer_time: None,
+            parents: vec![],
+            files: vec![],
+            bug_introducing_commits: vec![],
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn conventional_commit_headers() {
+        let header = parse_conventional_commit("Fix(Parser)!: handle empty input\n\nfeat: not this line").unwrap();
+        assert_eq!(header.kind, "fix");
+        assert_eq!(header.scope.as_deref(), Some("parser"));
+        let header = parse_conventional_commit("feat!: drop node 12").unwrap();
+        assert_eq!(header.kind, "feat");
+        assert_eq!(header.scope, None);
+        assert!(parse_conventional_commit("fix the parser").is_none());
+        assert!(parse_conventional_commit("update readme\n\nfix: only in the body").is_none());
+
+        let classifier = ConventionalCommitClassifier::new(&args(&["FIX"]), &args(&["parser"]));
+        assert!(classifier.is_bugfix("", &get_commit_record("fix(PARSER): empty input")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("fix(lexer): empty input")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("fix: empty input")));
+    }
+
+    #[test]
+    fn csv_lines_with_quotes() {
+        assert_eq!(split_csv_line("LINE-1,Bug"), ["LINE-1", "Bug"]);
+        assert_eq!(split_csv_line(r#""a, b",c"#), ["a, b", "c"]);
+        assert_eq!(split_csv_line(r#""say ""hi""",,x"#), [r#"say "hi""#, "", "x"]);
+    }
+
+    #[test]
+    fn issue_keys_in_the_whole_message() {
+        assert_eq!(normalize_issue_key(" line-123 "), "LINE-123");
+        assert_eq!(normalize_issue_key("#123"), "123");
+
+        let path = std::env::temp_dir().join(format!("gitdiffjson_issues_{}.csv", std::process::id()));
+        fs::write(&path, "Issue key,Summary,Issue Type\nLINE-1,\"crash, on start\",Bug\nLINE-2,docs,Task\n77,typo,bug\n").unwrap();
+        let classifier = IssueTrackerClassifier::from_file(path.to_str().unwrap(), &args(&["bug"])).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(classifier.is_bugfix("", &get_commit_record("handle missing config (line-1)")));
+        assert!(classifier.is_bugfix("", &get_commit_record("handle missing config\n\nCloses #77")));
+        assert!(classifier.is_bugfix("", &get_commit_record("handle missing config\n\nRefs: LINE-1")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("update docs\n\nRefs: LINE-2")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("bump to 1.77")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("fix #7")));
+    }
+
+    #[test]
+    fn regexes_match_the_first_line() {
+        let classifier = RegexClassifier::new(vec![Regex::new("(?i)^fix").unwrap()]);
+        assert!(classifier.is_bugfix("", &get_commit_record("Fix crash\n\nsome details")));
+        assert!(!classifier.is_bugfix("", &get_commit_record("add feature\n\nfix later")));
+    }
+
+    #[test]
+    fn bugfix_classifier_errors() {
+        let regexes = [Regex::new("fix").unwrap()];
+        let error = |flags: &[&str]| parse_bugfix_classifier(&args(flags), &regexes).err();
+        assert!(error(&[]).is_none());
+        assert!(error(&["bugfix=regex,conventional"]).is_none());
+        assert!(error(&["bugfix=regex,semantic"]).unwrap().contains("unknown bugfix classifier semantic"));
+        assert!(error(&["bugfix=issues"]).unwrap().contains("needs --issues"));
+        assert!(error(&["bugfix=,"]).unwrap().contains("at least one"));
+        assert!(error(&["issues=/nonexistent/issues.csv"]).unwrap().contains("can not read the issues"));
+    }
+}
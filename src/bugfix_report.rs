@@ -0,0 +1,345 @@
+use crate::bugfix::BugfixClassifier;
+use crate::raw_data::RawData;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+
+//A labelled commit that got the wrong class
+#[derive(Serialize, Clone)]
+pub struct LabelledCommit {
+    pub sha: String,
+    pub message: String,
+}
+
+//How well one classifier (or one regex of it) matches the hand labels, bugs are the positives
+#[derive(Serialize)]
+pub struct ClassifierQuality {
+    pub name: String,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+    //NaN (null in json) when undefined
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub accuracy: f64,
+    //labelled bugs that no other regex matches, what is lost without it. Only for the regexes
+    pub only_match: Option<usize>,
+    pub false_positive_commits: Vec<LabelledCommit>,
+    //only for the whole classifier, a single regex missing bugs the others find is expected
+    pub false_negative_commits: Vec<LabelledCommit>,
+}
+
+//What "bugfix_quality" writes
+#[derive(Serialize)]
+pub struct BugfixQualityReport {
+    pub labelled: usize,
+    //labelled shas that are not in the raw data, left out of everything
+    pub missing: Vec<String>,
+    pub classifier: ClassifierQuality,
+    pub regexes: Vec<ClassifierQuality>,
+}
+
+//"bug", "true", "1", "yes" and "fix" are bugs, "not_bug", "not bug", "false", "0", "no" and "other" are not
+fn parse_label(label: &str) -> Option<bool> {
+    match label.trim().trim_matches('"').to_lowercase().as_str() {
+        "bug" | "true" | "1" | "yes" | "fix" => Some(true),
+        "not_bug" | "not bug" | "notbug" | "false" | "0" | "no" | "other" => Some(false),
+        _ => None,
+    }
+}
+
+//Reads (sha, is bug) labels. json is {"<sha>": true | "bug" | "not_bug", ...}, anything else is "<sha>,<label>" lines
+//where a first line without a label (a header) is skipped. Shas can be abbreviated.
+pub fn read_labels(path: &str) -> Result<Vec<(String, bool)>, String> {
+    let file_string = fs::read_to_string(path).map_err(|err| format!("can not read the labels {}: {}", path, err))?;
+    if path.to_lowercase().ends_with(".json") {
+        let labels: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&file_string).map_err(|err| format!("can not parse the labels {}: {}", path, err))?;
+        return labels
+            .into_iter()
+            .map(|(sha, label)| {
+                let is_bug = match &label {
+                    serde_json::Value::Bool(is_bug) => Some(*is_bug),
+                    serde_json::Value::String(label) => parse_label(label),
+                    _ => None,
+                };
+                match is_bug {
+                    Some(is_bug) => Ok((sha, is_bug)),
+                    None => Err(format!("{} has the label {} for {}, use true/false or bug/not_bug", path, label, sha)),
+                }
+            })
+            .collect();
+    }
+    let mut labels = vec![];
+    for (i, line) in file_string.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (sha, label) = line.split_once(',').unwrap_or((line, ""));
+        match parse_label(label) {
+            Some(is_bug) => labels.push((sha.trim().to_string(), is_bug)),
+            None if i == 0 => {}
+            None => return Err(format!("line {} of {} has no bug or not_bug label: {}", i + 1, path, line)),
+        }
+    }
+    Ok(labels)
+}
+
+fn divide(numerator: usize, denominator: usize) -> f64 {
+    numerator as f64 / denominator as f64
+}
+
+//(full sha, message, is bug, classified as bug) for every labelled commit
+type Classification = (String, String, bool, bool);
+
+fn get_quality(name: &str, classifications: &[Classification], only_match: Option<usize>, list_false_negatives: bool) -> ClassifierQuality {
+    let count = |is_bug: bool, classified_bug: bool| {
+        classifications
+            .iter()
+            .filter(|(_, _, bug, classified)| *bug == is_bug && *classified == classified_bug)
+            .count()
+    };
+    let true_positives = count(true, true);
+    let false_positives = count(false, true);
+    let false_negatives = count(true, false);
+    let true_negatives = count(false, false);
+    let precision = divide(true_positives, true_positives + false_positives);
+    let recall = divide(true_positives, true_positives + false_negatives);
+    let get_commits = |is_bug: bool| {
+        classifications
+            .iter()
+            .filter(|(_, _, bug, classified)| *bug == is_bug && *classified != is_bug)
+            .map(|(sha, message, _, _)| LabelledCommit {
+                sha: sha.to_owned(),
+                message: message.to_owned(),
+            })
+            .collect()
+    };
+    ClassifierQuality {
+        name: name.to_string(),
+        true_positives,
+        false_positives,
+        false_negatives,
+        true_negatives,
+        precision,
+        recall,
+        f1: 2.0 * precision * recall / (precision + recall),
+        accuracy: divide(true_positives + true_negatives, classifications.len()),
+        only_match,
+        false_positive_commits: get_commits(false),
+        false_negative_commits: if list_false_negatives { get_commits(true) } else { vec![] },
+    }
+}
+
+//Classifies the labelled commits of raw_data with bugfix_classifier and with every regex of recognized_bugfix_indicators on its own.
+//Err is a message for the user, for an abbreviated sha that matches more than one commit
+pub fn get_bugfix_quality(
+    raw_data: &RawData,
+    labels: &[(String, bool)],
+    bugfix_classifier: &dyn BugfixClassifier,
+    recognized_bugfix_indicators: &[Regex],
+) -> Result<BugfixQualityReport, String> {
+    let mut missing = vec![];
+    //(full sha, first line of the message, is bug) of the labelled commits in the raw data
+    let mut labelled = vec![];
+    for (sha, is_bug) in labels {
+        let prefix = sha.to_lowercase();
+        let matches: Vec<&String> = raw_data.commits.keys().filter(|full_sha| full_sha.starts_with(&prefix)).collect();
+        match matches[..] {
+            [full_sha] => labelled.push((full_sha.to_owned(), raw_data.commits[full_sha].get_summary().to_owned(), *is_bug)),
+            [] => missing.push(sha.to_owned()),
+            _ => return Err(format!("the labelled sha {} matches {} commits, use a longer one", sha, matches.len())),
+        }
+    }
+
+    let classify = |is_bugfix: &dyn Fn(&str) -> bool| -> Vec<Classification> {
+        labelled
+            .iter()
+            .map(|(sha, message, is_bug)| (sha.to_owned(), message.to_owned(), *is_bug, is_bugfix(sha)))
+            .collect()
+    };
+    let classifier = get_quality(
+        "classifier",
+        &classify(&|sha| bugfix_classifier.is_bugfix(sha, &raw_data.commits[sha])),
+        None,
+        true,
+    );
+    let regexes = recognized_bugfix_indicators
+        .iter()
+        .enumerate()
+        .map(|(i, regex)| {
+            let classifications = classify(&|sha| regex.is_match(raw_data.commits[sha].get_summary()));
+            let only_match = labelled
+                .iter()
+                .filter(|(sha, message, is_bug)| {
+                    *is_bug
+                        && regex.is_match(message)
+                        && !recognized_bugfix_indicators
+                            .iter()
+                            .enumerate()
+                            .any(|(j, other)| j != i && other.is_match(raw_data.commits[sha].get_summary()))
+                })
+                .count();
+            get_quality(regex.as_str(), &classifications, Some(only_match), false)
+        })
+        .collect();
+    Ok(BugfixQualityReport {
+        labelled: labelled.len(),
+        missing,
+        classifier,
+        regexes,
+    })
+}
+
+fn format_quality(quality: &ClassifierQuality) -> String {
+    let mut text = format!(
+        "tp = {}, fp = {}, fn = {}, tn = {}, precision = {:.4}, recall = {:.4}, f1 = {:.4}, accuracy = {:.4}",
+        quality.true_positives,
+        quality.false_positives,
+        quality.false_negatives,
+        quality.true_negatives,
+        quality.precision,
+        quality.recall,
+        quality.f1,
+        quality.accuracy
+    );
+    if let Some(only_match) = quality.only_match {
+        let _ = write!(text, ", only match = {}", only_match);
+    }
+    text
+}
+
+impl BugfixQualityReport {
+    //The confusion matrix and scores of the classifier and of every regex, then the misclassified messages
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "labelled commits = {}", self.labelled);
+        if !self.missing.is_empty() {
+            let _ = writeln!(text, "not in the raw data = {} ({})", self.missing.len(), self.missing.join(", "));
+        }
+        let _ = writeln!(text, "\nclassifier > {}", format_quality(&self.classifier));
+        let _ = writeln!(text, "\nper regex (only match = labelled bugs no other regex matches)");
+        for regex in &self.regexes {
+            let _ = writeln!(text, "{} > {}", regex.name, format_quality(regex));
+        }
+        let _ = writeln!(text, "\nfalse positives of the classifier");
+        for commit in &self.classifier.false_positive_commits {
+            let _ = writeln!(text, "  {} {}", commit.sha, commit.message);
+        }
+        let _ = writeln!(text, "\nfalse negatives of the classifier");
+        for commit in &self.classifier.false_negative_commits {
+            let _ = writeln!(text, "  {} {}", commit.sha, commit.message);
+        }
+        for regex in self.regexes.iter().filter(|regex| !regex.false_positive_commits.is_empty()) {
+            let _ = writeln!(text, "\nfalse positives of {}", regex.name);
+            for commit in &regex.false_positive_commits {
+                let _ = writeln!(text, "  {} {}", commit.sha, commit.message);
+            }
+        }
+        text
+    }
+
+    //One row per misclassified commit: classifier ("classifier" or the regex), error (false_positive or false_negative), sha, message
+    pub fn to_csv(&self) -> String {
+        let quote = |value: &str| format!("\"{}\"", value.replace('"', "\"\""));
+        let mut csv = String::from("classifier,error,sha,message\n");
+        for quality in std::iter::once(&self.classifier).chain(&self.regexes) {
+            for (error, commits) in [
+                ("false_positive", &quality.false_positive_commits),
+                ("false_negative", &quality.false_negative_commits),
+            ] {
+                for commit in commits {
+                    let _ = writeln!(csv, "{},{},{},{}", quote(&quality.name), error, commit.sha, quote(&commit.message));
+                }
+            }
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bugfix::RegexClassifier;
+    use crate::raw_data::CommitRecord;
+
+    fn get_sha(prefix: &str) -> String {
+        format!("{:0<40}", prefix)
+    }
+
+    fn get_raw_data(messages: &[(&str, &str)]) -> RawData {
+        let mut raw_data = RawData::new();
+        for (age, (sha_prefix, message)) in messages.iter().enumerate() {
+            raw_data.commits.insert(
+                get_sha(sha_prefix),
+                CommitRecord {
+                    message: message.to_string(),
+                    age: age as i32,
+                    author_name: None,
+                    author_email: None,
+                    author_time: None,
+                    committer_time: None,
+                    parents: vec![],
+                    files: vec![],
+                    bug_introducing_commits: vec![],
+                },
+            );
+        }
+        raw_data
+    }
+
+    fn labels(labels: &[(&str, bool)]) -> Vec<(String, bool)> {
+        labels.iter().map(|(sha, is_bug)| (sha.to_string(), *is_bug)).collect()
+    }
+
+    #[test]
+    fn confusion_matrix_and_scores() {
+        let raw_data = get_raw_data(&[
+            ("aaaa1", "fix crash"),
+            ("aaaa2", "fix typo in the docs"),
+            ("bbbb", "resolve crash on start\n\nfix: not on the first line"),
+            ("cccc", "add feature"),
+            ("dddd", "handle empty input"),
+        ]);
+        let regexes = [Regex::new("^fix").unwrap(), Regex::new("crash").unwrap(), Regex::new("never").unwrap()];
+        let classifier = RegexClassifier::new(regexes.to_vec());
+        //abbreviated and upper case shas are found, ffff is not in the raw data
+        let labels = labels(&[("aaaa1", true), ("AAAA2", false), (&get_sha("bbbb"), true), ("cccc", false), ("dddd", true), ("ffff", true)]);
+        let report = get_bugfix_quality(&raw_data, &labels, &classifier, &regexes).unwrap();
+        assert_eq!(report.labelled, 5);
+        assert_eq!(report.missing, ["ffff"]);
+
+        let quality = &report.classifier;
+        let matrix = |quality: &ClassifierQuality| (quality.true_positives, quality.false_positives, quality.false_negatives, quality.true_negatives);
+        assert_eq!(matrix(quality), (2, 1, 1, 1));
+        assert_eq!((quality.precision, quality.recall, quality.f1, quality.accuracy), (2.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0, 0.6));
+        assert_eq!(quality.only_match, None);
+        assert_eq!(quality.false_positive_commits.iter().map(|commit| commit.sha.to_owned()).collect::<Vec<_>>(), [get_sha("aaaa2")]);
+        assert_eq!(quality.false_negative_commits.iter().map(|commit| commit.sha.to_owned()).collect::<Vec<_>>(), [get_sha("dddd")]);
+
+        let [fix, crash, never] = [&report.regexes[0], &report.regexes[1], &report.regexes[2]];
+        assert_eq!(matrix(fix), (1, 1, 2, 1));
+        assert_eq!((fix.precision, fix.recall), (0.5, 1.0 / 3.0));
+        //"fix crash" is matched by crash too
+        assert_eq!(fix.only_match, Some(0));
+        assert_eq!(matrix(crash), (2, 0, 1, 2));
+        assert_eq!((crash.precision, crash.recall, crash.f1), (1.0, 2.0 / 3.0, 0.8));
+        assert_eq!(crash.only_match, Some(1));
+        assert!(crash.false_negative_commits.is_empty());
+        assert_eq!(matrix(never), (0, 0, 3, 2));
+        assert!(never.precision.is_nan() && never.f1.is_nan());
+        assert_eq!(never.recall, 0.0);
+    }
+
+    #[test]
+    fn ambiguous_shas_are_errors() {
+        let raw_data = get_raw_data(&[("aaaa1", "fix crash"), ("aaaa2", "add feature")]);
+        let classifier = RegexClassifier::new(vec![]);
+        let error = get_bugfix_quality(&raw_data, &labels(&[("aaaa", true)]), &classifier, &[]).err().unwrap();
+        assert_eq!(error, "the labelled sha aaaa matches 2 commits, use a longer one");
+        assert!(get_bugfix_quality(&raw_data, &labels(&[("aaaa2", false)]), &classifier, &[]).is_ok());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::co_change::{MIN_CONFIDENCE, MIN_SUPPORT};
+use clap::{Args, Parser, Subcommand};
+use clap_complete::Shell;
+use std::fmt::Display;
+
+//The raw data "repo" writes and the other modes read by default
+pub const DEFAULT_RAW_DATA: &str = "generatedJson.json";
+
+const RAW_DATA_HELP: &str = "Raw data generated by \"repo\" [default: generatedJson.json, inside output_dir of the config when it has one]";
+const OUTPUT_HELP: &str = "Name of the written files, inside output_dir of the config when it has one";
+
+#[derive(Parser)]
+#[command(name = "gitdiffjson", version, about = "Finds the files most likely to get bugs in a git repository from its history")]
+pub struct Cli {
+    #[arg(long, global = true, env = "GITDIFFJSON_CONFIG", help = "Config file (.toml or .json), defaults to gitdiffjson.toml when it exists")]
+    pub config: Option<String>,
+    #[arg(long, global = true, env = "GITDIFFJSON_PROFILE", help = "Profile of the config file to use")]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Generate raw data from a git repository")]
+    Repo {
+        #[arg(help = "Path to the git repository")]
+        repo_path: String,
+        #[command(flatten)]
+        repo: RepoArgs,
+        #[command(flatten)]
+        bugfix: BugfixArgs,
+    },
+    #[command(name = "repo_incremental", about = "Add the new commits of a git repository to existing raw data")]
+    RepoIncremental {
+        #[arg(help = "Path to the git repository")]
+        repo_path: String,
+        #[arg(long, help = "Raw data to update [default: generatedJson.json, inside output_dir of the config when it has one]")]
+        raw_data: Option<String>,
+        #[command(flatten)]
+        repo: RepoArgs,
+        #[command(flatten)]
+        bugfix: BugfixArgs,
+    },
+    #[command(about = "Find the commits that introduced the bugs of the bugfixes in raw data (SZZ)")]
+    Szz {
+        #[arg(help = "Path to the git repository")]
+        repo_path: String,
+        #[arg(long, help = "Raw data to update [default: generatedJson.json, inside output_dir of the config when it has one]")]
+        raw_data: Option<String>,
+        #[command(flatten)]
+        bugfix: BugfixArgs,
+    },
+    #[command(name = "multi_analysis", about = "Rank all factors by how many bugs they find over the cutoffs")]
+    MultiAnalysis {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "analysis", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, help = "Also write a log with every run")]
+        logs: bool,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+        #[command(flatten)]
+        grid: GridArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    #[command(about = "Write how many bugs every factor finds at one cutoff")]
+    Text {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "text", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, alias = "cuttof", help = "Cutoff in % of the repository age")]
+        cutoff: usize,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+        #[command(flatten)]
+        grid: GridArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    #[command(about = "Convert raw data into d3 treemap jsons ranked by a factor")]
+    D3 {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "full", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, default_value = "files", value_parser = ["files", "full"], help = "files: only files, full: the folder structure (slow)")]
+        view: String,
+        #[arg(long, help = "Slug (or number) of the factor to rank by, the \"factors\" subcommand lists them")]
+        factor: String,
+        #[arg(long, default_value_t = 100, help = "Number of files in the files page")]
+        items: usize,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    #[command(about = "Write the file and function objects at a cutoff as json")]
+    Classes {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "classes", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, alias = "cuttof", help = "Cutoff in % of the repository age")]
+        cutoff: usize,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    #[command(about = "Rank the factors over several repositories")]
+    Aggregate {
+        #[arg(required = true, help = "Raw data or multi_analysis json results, one per repository")]
+        paths: Vec<String>,
+        #[arg(long, default_value = "aggregate", help = OUTPUT_HELP)]
+        output: String,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+        #[command(flatten)]
+        grid: GridArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    #[command(about = "Fit a linear or logistic factor on early cutoffs and test it on later ones")]
+    Fit {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "fit", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, default_value = "linear", value_parser = ["linear", "logistic"])]
+        model: String,
+        #[arg(long, value_delimiter = ',', default_value = "5,10,15,20,25,30,35,40", help = "Cutoffs to fit on")]
+        train: Vec<usize>,
+        #[arg(long, value_delimiter = ',', default_value = "45,50,55,60,65,70,75", help = "Cutoffs to test on")]
+        test: Vec<usize>,
+        #[arg(long, help = "Factors file to save the fitted factor in, \"factors.json\" to use it in the other subcommands")]
+        factors_out: Option<String>,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    #[command(about = "Print all factors with their slug, number, description and formula as json")]
+    Factors,
+    #[command(about = "Write which files change together as a graph")]
+    Coupling {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "coupling", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, default_value_t = MIN_SUPPORT, help = "Commits two files need together to be an edge")]
+        min_support: u32,
+        #[arg(long, default_value_t = MIN_CONFIDENCE, help = "Share of the commits of a file that also changed the other to be an edge")]
+        min_confidence: f32,
+        #[arg(long, help = "Also print the files most coupled to this file")]
+        file: Option<String>,
+        #[arg(long, default_value_t = 10, help = "Number of files printed for --file")]
+        top: usize,
+    },
+    #[command(name = "bugfix_quality", about = "Measure the bugfix classifier and its regexes against hand labelled commits")]
+    BugfixQuality {
+        #[arg(long, help = RAW_DATA_HELP)]
+        raw_data: Option<String>,
+        #[arg(long, default_value = "bugfix", help = OUTPUT_HELP)]
+        output: String,
+        #[arg(long, help = "csv of sha,bug|not_bug lines or json {\"<sha>\": true|false}")]
+        labels: String,
+        #[command(flatten)]
+        bugfix: BugfixArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    #[command(about = "Print a shell completion script")]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+//key=value options for the option parsers, from the flags that are given
+fn push_option(options: &mut Vec<String>, key: &str, value: &Option<impl Display>) {
+    if let Some(value) = value {
+        options.push(format!("{}={}", key, value));
+    }
+}
+
+fn push_list(options: &mut Vec<String>, key: &str, values: &[impl Display]) {
+    if !values.is_empty() {
+        options.push(format!("{}={}", key, values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(",")));
+    }
+}
+
+fn push_flag(options: &mut Vec<String>, key: &str, flag: bool) {
+    if flag {
+        options.push(format!("{}=true", key));
+    }
+}
+
+//How "repo" walks the history
+#[derive(Args)]
+pub struct RepoArgs {
+    #[arg(long = "ref", help = "Branch, tag or commit to start from [default: HEAD]")]
+    pub start_ref: Option<String>,
+    #[arg(long, value_parser = ["git", "time", "topo", "topo_time"], help = "Commit order [default: git]")]
+    pub order: Option<String>,
+    #[arg(long, help = "Only follow the first parent of merges")]
+    pub first_parent: bool,
+    #[arg(long, value_parser = ["skip", "first_parent", "all_parents"], help = "What merge commits are diffed against [default: first_parent]")]
+    pub merges: Option<String>,
+    #[arg(long, help = "Resolve authors through the .mailmap of the repository")]
+    pub mailmap: bool,
+    #[arg(long, help = "Also find the bug introducing commits (see szz)")]
+    pub szz: bool,
+}
+
+impl RepoArgs {
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        push_option(&mut options, "ref", &self.start_ref);
+        push_option(&mut options, "order", &self.order);
+        push_flag(&mut options, "first_parent", self.first_parent);
+        push_option(&mut options, "merges", &self.merges);
+        push_flag(&mut options, "mailmap", self.mailmap);
+        push_flag(&mut options, "szz", self.szz);
+        options
+    }
+}
+
+//What makes a commit a bugfix
+#[derive(Args)]
+pub struct BugfixArgs {
+    #[arg(long, value_delimiter = ',', value_parser = ["regex", "conventional", "issues"], help = "Classifiers to combine [default: regex, regex,issues with --issues]")]
+    pub bugfix: Vec<String>,
+    #[arg(long, value_delimiter = ',', help = "Conventional commit types that are bugfixes [default: fix]")]
+    pub fix_types: Vec<String>,
+    #[arg(long, value_delimiter = ',', help = "Only these conventional commit scopes")]
+    pub fix_scopes: Vec<String>,
+    #[arg(long, help = "Issue tracker export (.json or .csv)")]
+    pub issues: Option<String>,
+    #[arg(long, value_delimiter = ',', help = "Issue types that are bugs [default: bug]")]
+    pub bug_types: Vec<String>,
+}
+
+impl BugfixArgs {
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        push_list(&mut options, "bugfix", &self.bugfix);
+        push_list(&mut options, "fix_types", &self.fix_types);
+        push_list(&mut options, "fix_scopes", &self.fix_scopes);
+        push_option(&mut options, "issues", &self.issues);
+        push_list(&mut options, "bug_types", &self.bug_types);
+        options
+    }
+}
+
+//The ages, bugfixes and ground truth every analysis uses
+#[derive(Args)]
+pub struct AnalysisArgs {
+    #[arg(long, value_parser = ["position", "days", "author_days"], help = "What the age of a commit is [default: position]")]
+    pub ages: Option<String>,
+    #[arg(long, help = "Age for a commit to count half in the decayed factors [default: 10% of the repository age]")]
+    pub half_life: Option<f32>,
+    #[arg(long, value_parser = ["fixes", "introduced"], help = "What the bugs after the cutoff are [default: fixes]")]
+    pub truth: Option<String>,
+    #[command(flatten)]
+    pub bugfix: BugfixArgs,
+}
+
+impl AnalysisArgs {
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        push_option(&mut options, "ages", &self.ages);
+        push_option(&mut options, "half_life", &self.half_life);
+        push_option(&mut options, "truth", &self.truth);
+        options.extend(self.bugfix.to_options());
+        options
+    }
+}
+
+//The cutoffs and breakpoints
+#[derive(Args)]
+pub struct GridArgs {
+    #[arg(long, help = "json file with the grid")]
+    pub grid: Option<String>,
+    #[arg(long, value_delimiter = ',', alias = "cuttofs", help = "Cutoffs in % of the repository age")]
+    pub cutoffs: Vec<usize>,
+    #[arg(long, value_delimiter = ',', help = "Windows as train_start:cutoff:test_end")]
+    pub windows: Vec<String>,
+    #[arg(long, value_delimiter = ',', help = "Breakpoints in % of the files")]
+    pub breakpoints: Vec<f64>,
+    #[arg(long, value_delimiter = ',', help = "Breakpoints in numbers of files")]
+    pub top_n: Vec<usize>,
+    #[arg(long, help = "Only count bugs fixed in this many commits after the cutoff")]
+    pub horizon: Option<usize>,
+    #[arg(long, help = "Only count bugs fixed in this many days after the cutoff")]
+    pub horizon_days: Option<usize>,
+}
+
+impl GridArgs {
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        push_option(&mut options, "grid", &self.grid);
+        push_list(&mut options, "cutoffs", &self.cutoffs);
+        push_list(&mut options, "windows", &self.windows);
+        push_list(&mut options, "breakpoints", &self.breakpoints);
+        push_list(&mut options, "top_n", &self.top_n);
+        push_option(&mut options, "horizon", &self.horizon);
+        push_option(&mut options, "horizon_days", &self.horizon_days);
+        options
+    }
+}
+
+#[derive(Args)]
+pub struct FormatArgs {
+    #[arg(long, value_parser = ["text", "json", "csv"], help = "Format of the report [default: text]")]
+    pub format: Option<String>,
+}
+
+impl FormatArgs {
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        push_option(&mut options, "format", &self.format);
+        options
+    }
+}
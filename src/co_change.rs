@@ -0,0 +1,238 @@
+use crate::raw_data::{RawData, RenameMap};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+//Commits changing more files than this are left out, they are mostly formatting/moves and would couple everything
+pub const MAX_FILES_PER_COMMIT: usize = 30;
+//Defaults for when two files count as coupled
+pub const MIN_SUPPORT: u32 = 2;
+pub const MIN_CONFIDENCE: f32 = 0.5;
+
+//Logical coupling between files, from how often they are changed in the same commit.
+//support is the number of commits changing both files, confidence a => b is support / commits changing a.
+pub struct CoChange {
+    //file -> commits changing it
+    commit_counts: HashMap<String, u32>,
+    //file -> other file -> commits changing both
+    pair_counts: HashMap<String, HashMap<String, u32>>,
+}
+
+//Coupling graph written by the "coupling" mode, in the nodes/links shape d3 force layouts use
+#[derive(Serialize)]
+pub struct CouplingGraph {
+    nodes: Vec<CouplingNode>,
+    links: Vec<CouplingLink>,
+}
+
+#[derive(Serialize)]
+struct CouplingNode {
+    id: String,
+    commits: u32,
+}
+
+#[derive(Serialize)]
+struct CouplingLink {
+    source: String,
+    target: String,
+    support: u32,
+    //source => target
+    confidence: f32,
+    //target => source
+    reverse_confidence: f32,
+}
+
+impl CoChange {
+    pub fn new() -> CoChange {
+        CoChange {
+            commit_counts: HashMap::new(),
+            pair_counts: HashMap::new(),
+        }
+    }
+
+    //Counts the commits with min_age <= age <= max_age, files are counted under their current name and filtered_filetypes are left out
+    pub fn from_raw_data(
+        raw_data: &RawData,
+        rename_map: &RenameMap,
+        filtered_filetypes: &[Regex],
+        min_age: i32,
+        max_age: i32,
+    ) -> CoChange {
+        let mut co_change = CoChange::new();
+        for (sha, commit) in &raw_data.commits {
+            if commit.age < min_age || commit.age > max_age {
+                continue;
+            }
+            let files: Vec<String> = commit
+                .files
+                .iter()
+                .map(|file| rename_map.get_current_name(&file.path, sha))
+                .filter(|filename| !filtered_filetypes.iter().any(|regex| regex.is_match(filename)))
+                .collect();
+            co_change.add_commit(&files);
+        }
+        co_change
+    }
+
+    pub fn add_commit(&mut self, files: &[String]) {
+        let files: HashSet<&String> = files.iter().collect();
+        if files.len() > MAX_FILES_PER_COMMIT {
+            return;
+        }
+        for file in &files {
+            *self.commit_counts.entry(file.to_string()).or_insert(0) += 1;
+            for other in &files {
+                if file != other {
+                    *self
+                        .pair_counts
+                        .entry(file.to_string())
+                        .or_default()
+                        .entry(other.to_string())
+                        .or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    pub fn get_support(&self, file: &str, other: &str) -> u32 {
+        self.pair_counts
+            .get(file)
+            .and_then(|pairs| pairs.get(other))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    //How often a change to file also changed other
+    pub fn get_confidence(&self, file: &str, other: &str) -> f32 {
+        match self.commit_counts.get(file) {
+            Some(commits) => self.get_support(file, other) as f32 / *commits as f32,
+            None => 0.0,
+        }
+    }
+
+    //The n files most coupled to file as (file, support, confidence), highest confidence first
+    pub fn get_most_coupled(&self, file: &str, n: usize) -> Vec<(String, u32, f32)> {
+        let mut coupled: Vec<(String, u32, f32)> = match self.pair_counts.get(file) {
+            Some(pairs) => pairs
+                .iter()
+                .map(|(other, support)| (other.to_owned(), *support, self.get_confidence(file, other)))
+                .collect(),
+            None => vec![],
+        };
+        coupled.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+        coupled.truncate(n);
+        coupled
+    }
+
+    //Files changed together with file at least min_support times and in at least min_confidence of its commits
+    pub fn get_coupled_files(&self, file: &str, min_support: u32, min_confidence: f32) -> Vec<&String> {
+        match self.pair_counts.get(file) {
+            Some(pairs) => pairs
+                .iter()
+                .filter(|(other, support)| {
+                    **support >= min_support && self.get_confidence(file, other) >= min_confidence
+                })
+                .map(|(other, _)| other)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    //Every pair with enough support where either direction has enough confidence, as one link per pair
+    pub fn get_graph(&self, min_support: u32, min_confidence: f32) -> CouplingGraph {
+        let mut links = vec![];
+        let mut linked_files: HashSet<&String> = HashSet::new();
+        for (file, pairs) in &self.pair_counts {
+            for (other, support) in pairs {
+                if file >= other || *support < min_support {
+                    continue;
+                }
+                let confidence = self.get_confidence(file, other);
+                let reverse_confidence = self.get_confidence(other, file);
+                if confidence.max(reverse_confidence) < min_confidence {
+                    continue;
+                }
+                linked_files.insert(file);
+                linked_files.insert(other);
+                links.push(CouplingLink {
+                    source: file.to_owned(),
+                    target: other.to_owned(),
+                    support: *support,
+                    confidence,
+                    reverse_confidence,
+                });
+            }
+        }
+        links.sort_by(|a, b| b.support.cmp(&a.support).then(a.source.cmp(&b.source)).then(a.target.cmp(&b.target)));
+        let mut nodes: Vec<CouplingNode> = linked_files
+            .into_iter()
+            .map(|file| CouplingNode {
+                id: file.to_owned(),
+                commits: self.commit_counts[file],
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        CouplingGraph { nodes, links }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    //a and b are changed together twice, a and c once, b once on its own
+    fn get_co_change() -> CoChange {
+        let mut co_change = CoChange::new();
+        co_change.add_commit(&files(&["a", "b"]));
+        //a file listed twice is one change
+        co_change.add_commit(&files(&["a", "b", "a"]));
+        co_change.add_commit(&files(&["a", "c"]));
+        co_change.add_commit(&files(&["b"]));
+        co_change
+    }
+
+    #[test]
+    fn support_and_confidence() {
+        let co_change = get_co_change();
+        assert_eq!(co_change.get_support("a", "b"), 2);
+        assert_eq!(co_change.get_support("b", "a"), 2);
+        assert_eq!(co_change.get_support("b", "c"), 0);
+        assert_eq!(co_change.get_confidence("a", "b"), 2.0 / 3.0);
+        assert_eq!(co_change.get_confidence("c", "a"), 1.0);
+        assert_eq!(co_change.get_confidence("d", "a"), 0.0);
+        assert_eq!(co_change.get_most_coupled("a", 5), [("b".to_string(), 2, 2.0 / 3.0), ("c".to_string(), 1, 1.0 / 3.0)]);
+        assert_eq!(co_change.get_most_coupled("a", 1).len(), 1);
+    }
+
+    #[test]
+    fn coupled_files_need_support_and_confidence() {
+        let co_change = get_co_change();
+        assert_eq!(co_change.get_coupled_files("a", MIN_SUPPORT, MIN_CONFIDENCE), ["b"]);
+        //c always changes with a, but only once
+        assert!(co_change.get_coupled_files("c", MIN_SUPPORT, MIN_CONFIDENCE).is_empty());
+        assert_eq!(co_change.get_coupled_files("c", 1, MIN_CONFIDENCE), ["a"]);
+        assert!(co_change.get_coupled_files("a", 1, 0.9).is_empty());
+
+        let graph = co_change.get_graph(MIN_SUPPORT, MIN_CONFIDENCE);
+        assert_eq!(graph.links.len(), 1);
+        assert_eq!((graph.links[0].source.as_str(), graph.links[0].target.as_str(), graph.links[0].support), ("a", "b", 2));
+        assert_eq!(graph.nodes.iter().map(|node| (node.id.as_str(), node.commits)).collect::<Vec<_>>(), [("a", 3), ("b", 3)]);
+    }
+
+    #[test]
+    fn large_commits_are_left_out() {
+        let mut co_change = CoChange::new();
+        let mut names: Vec<String> = (0..MAX_FILES_PER_COMMIT).map(|i| format!("file{}", i)).collect();
+        co_change.add_commit(&names);
+        assert_eq!(co_change.get_support("file0", "file1"), 1);
+        names.push("one too many".to_string());
+        co_change.add_commit(&names);
+        assert_eq!(co_change.get_support("file0", "file1"), 1);
+        assert_eq!(co_change.get_confidence("file0", "file1"), 1.0);
+        assert_eq!(co_change.get_support("file0", "one too many"), 0);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::cli::{AnalysisArgs, BugfixArgs, FormatArgs, GridArgs, RepoArgs};
+use crate::factors::FactorEntry;
+use crate::grid::GridFile;
+use clap::{Args, Command};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+//The config file used when there is no --config or GITDIFFJSON_CONFIG
+pub const DEFAULT_CONFIG_PATH: &str = "gitdiffjson.toml";
+
+//Modes that walk a git repository, they get the "repo" options of the config instead of "options"
+const REPO_MODES: [&str; 3] = ["repo", "repo_incremental", "szz"];
+
+//What the config file (and every profile in it) can have, every field is optional
+#[derive(Deserialize, Default, Clone)]
+struct ConfigSection {
+    //regexes for the commit messages of bugfixes, replaces "regex_recognized_bugfixes.json"
+    bugfix_patterns: Option<Vec<String>>,
+    //regexes for the paths of files to leave out, replaces "regex_filtered_file_types.json"
+    filtered_file_types: Option<Vec<String>>,
+    //added on top of the default factors and "factors.json", like its entries
+    factors: Option<Vec<FactorEntry>>,
+    grid: Option<GridFile>,
+    //directory the reports and raw data are written to
+    output_dir: Option<String>,
+    //key=value options of "repo", "repo_incremental" and "szz", like "merges" or "first_parent"
+    repo: Option<BTreeMap<String, serde_json::Value>>,
+    //key=value options of the other modes, like "ages" or "bugfix"
+    options: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(flatten)]
+    base: ConfigSection,
+    #[serde(default)]
+    profiles: BTreeMap<String, ConfigSection>,
+}
+
+//The config file with the profile applied, what main() uses instead of the regex json files and as defaults for the args
+pub struct Config {
+    section: ConfigSection,
+}
+
+//Reads a list of regexes from a json file like "regex_recognized_bugfixes.json", None when it does not exist or is empty
+fn read_regex_file(path: &str) -> Result<Option<Vec<String>>, String> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let file_string = fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    let regexes: Vec<String> = serde_json::from_str(&file_string).map_err(|err| format!("{} is not a json list of regexes: {}", path, err))?;
+    if regexes.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(regexes))
+    }
+}
+
+//patterns of the config, or the regex file, or the defaults
+fn get_regexes(patterns: &Option<Vec<String>>, regex_file: &str, defaults: &[&str]) -> Result<Vec<Regex>, String> {
+    let (patterns, source) = match patterns {
+        Some(patterns) => (patterns.clone(), "the config"),
+        None => match read_regex_file(regex_file)? {
+            Some(patterns) => (patterns, regex_file),
+            None => (defaults.iter().map(|pattern| pattern.to_string()).collect(), "the defaults"),
+        },
+    };
+    patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|err| format!("invalid regex {} in {}: {}", pattern, source, err)))
+        .collect()
+}
+
+//"true", "90" and "bug,defect" for true, 90 and ["bug", "defect"], what the option parsers read
+fn value_to_arg(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.to_owned(),
+        serde_json::Value::Array(values) => values.iter().map(value_to_arg).collect::<Vec<String>>().join(","),
+        value => value.to_string(),
+    }
+}
+
+//Overrides the fields of base that are in profile, "repo" and "options" are merged per key
+fn apply_profile(base: &mut ConfigSection, profile: ConfigSection) {
+    let merge = |base: &mut Option<BTreeMap<String, serde_json::Value>>, profile: Option<BTreeMap<String, serde_json::Value>>| {
+        if let Some(profile) = profile {
+            base.get_or_insert_with(BTreeMap::new).extend(profile);
+        }
+    };
+    merge(&mut base.repo, profile.repo);
+    merge(&mut base.options, profile.options);
+    base.bugfix_patterns = profile.bugfix_patterns.or(base.bugfix_patterns.take());
+    base.filtered_file_types = profile.filtered_file_types.or(base.filtered_file_types.take());
+    base.factors = profile.factors.or(base.factors.take());
+    base.grid = profile.grid.or(base.grid.take());
+    base.output_dir = profile.output_dir.or(base.output_dir.take());
+}
+
+//The flag names (with "_" for "-") of the flag groups that become key=value options, the keys the "repo" table (repo_table) or the "options" table can have.
+//Flags like --output or --factor are only read from the command line
+fn get_option_keys(repo_table: bool) -> Vec<String> {
+    let command = Command::new("options");
+    let command = if repo_table {
+        BugfixArgs::augment_args(RepoArgs::augment_args(command))
+    } else {
+        FormatArgs::augment_args(GridArgs::augment_args(AnalysisArgs::augment_args(command)))
+    };
+    command
+        .get_arguments()
+        .flat_map(|argument| argument.get_all_aliases().unwrap_or_default().into_iter().chain(argument.get_long()))
+        .map(|long| long.replace('-', "_"))
+        .collect()
+}
+
+fn check_option_keys(options: &Option<BTreeMap<String, serde_json::Value>>, table: &str, path: &str) -> Result<(), String> {
+    let known_keys = get_option_keys(table == "repo");
+    match options.iter().flatten().find(|(key, _)| !known_keys.contains(key)) {
+        Some((key, _)) => Err(format!("unknown option {} in [{}] of {}, the keys are {}", key, table, path, known_keys.join(", "))),
+        None => Ok(()),
+    }
+}
+
+impl Config {
+    //Reads the config in path (--config or GITDIFFJSON_CONFIG, "gitdiffjson.toml" when it is not given but exists) with the profile
+    //(--profile or GITDIFFJSON_PROFILE) applied. .json files are json, anything else toml. No config file is an empty config.
+    //Err is a message for the user, like for an invalid flag
+    pub fn load(path: Option<String>, profile: Option<String>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => DEFAULT_CONFIG_PATH.to_string(),
+            None if profile.is_some() => return Err(format!("--profile needs a config file, {} or --config <path>", DEFAULT_CONFIG_PATH)),
+            None => return Ok(Config { section: ConfigSection::default() }),
+        };
+        let file_string = fs::read_to_string(&path).map_err(|err| format!("can not read the config {}: {}", path, err))?;
+        let mut config: ConfigFile = if path.to_lowercase().ends_with(".json") {
+            serde_json::from_str(&file_string).map_err(|err| format!("invalid config {}: {}", path, err))?
+        } else {
+            toml::from_str(&file_string).map_err(|err| format!("invalid config {}: {}", path, err))?
+        };
+        if let Some(name) = profile {
+            match config.profiles.remove(&name) {
+                Some(profile) => apply_profile(&mut config.base, profile),
+                None => {
+                    return Err(format!(
+                        "no profile {} in {}, it has {}",
+                        name,
+                        path,
+                        config.profiles.keys().cloned().collect::<Vec<String>>().join(", ")
+                    ))
+                }
+            }
+        }
+        check_option_keys(&config.base.repo, "repo", &path)?;
+        check_option_keys(&config.base.options, "options", &path)?;
+        eprintln!("using config {}", path);
+        Ok(Config { section: config.base })
+    }
+
+    //bugfix_patterns of the config, or "regex_recognized_bugfixes.json", or the defaults
+    pub fn get_recognized_bugfix_indicators(&self, defaults: &[&str]) -> Result<Vec<Regex>, String> {
+        get_regexes(&self.section.bugfix_patterns, "regex_recognized_bugfixes.json", defaults)
+    }
+
+    //filtered_file_types of the config, or "regex_filtered_file_types.json", or the defaults
+    pub fn get_filtered_file_types(&self, defaults: &[&str]) -> Result<Vec<Regex>, String> {
+        get_regexes(&self.section.filtered_file_types, "regex_filtered_file_types.json", defaults)
+    }
+
+    pub fn get_factor_entries(&self) -> Vec<FactorEntry> {
+        self.section.factors.clone().unwrap_or_default()
+    }
+
+    //options (key=value) with the options of the config for mode added after them, options already given are kept as they are
+    pub fn with_options(&self, mode: &str, options: Vec<String>) -> Vec<String> {
+        let has_arg = |key: &str| options.iter().any(|arg| arg.split_once('=').is_some_and(|(arg_key, _)| arg_key == key));
+        let config_options = if REPO_MODES.contains(&mode) {
+            &self.section.repo
+        } else {
+            &self.section.options
+        };
+        let mut config_args: Vec<String> = config_options
+            .iter()
+            .flatten()
+            .filter(|(key, _)| !has_arg(key))
+            .map(|(key, value)| format!("{}={}", key, value_to_arg(value)))
+            .collect();
+        //a "grid=" file is the whole grid, the grid of the config is left out then
+        if let (Some(grid), false) = (&self.section.grid, REPO_MODES.contains(&mode) || has_arg("grid")) {
+            config_args.extend(grid.to_args().into_iter().filter(|arg| !has_arg(arg.split_once('=').unwrap().0)));
+        }
+        [options, config_args].concat()
+    }
+
+    //name inside output_dir (which is created) when the config has one and name is relative
+    pub fn get_output_path(&self, name: &str) -> String {
+        match &self.section.output_dir {
+            Some(output_dir) if Path::new(name).is_relative() => {
+                fs::create_dir_all(output_dir).unwrap();
+                Path::new(output_dir).join(name).to_string_lossy().to_string()
+            }
+            _ => name.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_config(toml_string: &str) -> ConfigFile {
+        toml::from_str(toml_string).unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn profiles_replace_fields_and_merge_options() {
+        let mut config = parse_config(
+            r#"
+            bugfix_patterns = ["^fix"]
+            output_dir = "results"
+            [repo]
+            merges = "skip"
+            order = "time"
+            [options]
+            ages = "days"
+            [profiles.vue]
+            output_dir = "vue"
+            [profiles.vue.repo]
+            merges = "keep"
+            "#,
+        );
+        let profile = config.profiles.remove("vue").unwrap();
+        apply_profile(&mut config.base, profile);
+        let config = Config { section: config.base };
+        assert_eq!(config.with_options("repo", vec![]), ["merges=keep", "order=time"]);
+        assert_eq!(config.with_options("text", vec![]), ["ages=days"]);
+        assert_eq!(config.section.bugfix_patterns, Some(vec!["^fix".to_string()]));
+        assert_eq!(config.section.output_dir.as_deref(), Some("vue"));
+    }
+
+    #[test]
+    fn flags_win_over_the_config() {
+        let config = Config {
+            section: parse_config(
+                r#"
+                [options]
+                ages = "days"
+                bug_types = ["bug", "defect"]
+                [grid]
+                cutoffs = [10, 20]
+                top_n = [5]
+                "#,
+            )
+            .base,
+        };
+        assert_eq!(
+            config.with_options("text", args(&["ages=position", "top_n=50"])),
+            ["ages=position", "top_n=50", "bug_types=bug,defect", "cutoffs=10,20"]
+        );
+        //a grid file is the whole grid
+        assert_eq!(config.with_options("text", args(&["grid=grid.json"])), ["grid=grid.json", "ages=days", "bug_types=bug,defect"]);
+        //the repo modes have no grid and read [repo]
+        assert!(config.with_options("szz", vec![]).is_empty());
+    }
+
+    #[test]
+    fn only_the_options_the_parsers_read() {
+        let check = |toml_string: &str| {
+            let config = parse_config(toml_string);
+            check_option_keys(&config.base.repo, "repo", "test.toml").and(check_option_keys(&config.base.options, "options", "test.toml"))
+        };
+        assert!(check("[options]\nhalf_life = 30\ncuttofs = [10]\nhorizon_days = 90\nformat = \"csv\"\nbug_types = \"bug\"").is_ok());
+        assert!(check("[repo]\nref = \"main\"\nfirst_parent = true\nbugfix = \"conventional\"").is_ok());
+        for key in ["output", "factor", "items", "model", "train", "test", "min_support", "logs", "view", "raw_data"] {
+            let error = check(&format!("[options]\n{} = \"x\"", key)).unwrap_err();
+            assert!(error.starts_with(&format!("unknown option {} in [options]", key)), "{}", error);
+        }
+        assert!(check("[repo]\nages = \"days\"").is_err());
+        assert!(check("[options]\nmerges = \"skip\"").is_err());
+    }
+}
@@ -0,0 +1,585 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+//Values of a file a factor formula can use
+#[derive(Clone, Copy)]
+pub enum Variable {
+    Freq,
+    Bugs,
+    AgedFreq,
+    AgedBugs,
+    Oldest,
+    Newest,
+    DecayedFreq,
+    DecayedBugs,
+    Churn,
+    BugChurn,
+    Loc,
+    Authors,
+    MinorAuthors,
+    TopOwner,
+    CoupledFiles,
+    CoupledBugs,
+    BugsIntroduced,
+    MaxAge,
+}
+
+//Names of the variables in formulas
+pub const VARIABLE_NAMES: [(&str, Variable); 18] = [
+    ("freq", Variable::Freq),
+    ("bugs", Variable::Bugs),
+    ("aged_freq", Variable::AgedFreq),
+    ("aged_bugs", Variable::AgedBugs),
+    ("oldest", Variable::Oldest),
+    ("newest", Variable::Newest),
+    ("decayed_freq", Variable::DecayedFreq),
+    ("decayed_bugs", Variable::DecayedBugs),
+    ("churn", Variable::Churn),
+    ("bug_churn", Variable::BugChurn),
+    ("loc", Variable::Loc),
+    ("authors", Variable::Authors),
+    ("minor_authors", Variable::MinorAuthors),
+    ("top_owner", Variable::TopOwner),
+    ("coupled_files", Variable::CoupledFiles),
+    ("coupled_bugs", Variable::CoupledBugs),
+    ("bugs_introduced", Variable::BugsIntroduced),
+    ("max_age", Variable::MaxAge),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Copy)]
+enum Function {
+    Log,
+    Exp,
+    Pow,
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+}
+
+//name, function, number of arguments
+const FUNCTIONS: [(&str, Function, usize); 7] = [
+    ("log", Function::Log, 1),
+    ("exp", Function::Exp, 1),
+    ("pow", Function::Pow, 2),
+    ("sqrt", Function::Sqrt, 1),
+    ("abs", Function::Abs, 1),
+    ("min", Function::Min, 2),
+    ("max", Function::Max, 2),
+];
+
+enum Expression {
+    Number(f32),
+    Variable(Variable),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, variables: &dyn Fn(Variable) -> f32) -> f32 {
+        match self {
+            Expression::Number(number) => *number,
+            Expression::Variable(variable) => variables(*variable),
+            Expression::Negate(expression) => -expression.evaluate(variables),
+            Expression::Binary(operator, left, right) => {
+                let left = left.evaluate(variables);
+                let right = right.evaluate(variables);
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide => left / right,
+                    Operator::Power => left.powf(right),
+                }
+            }
+            Expression::Call(function, arguments) => {
+                let arguments: Vec<f32> = arguments.iter().map(|argument| argument.evaluate(variables)).collect();
+                match function {
+                    Function::Log => arguments[0].ln(),
+                    Function::Exp => arguments[0].exp(),
+                    Function::Pow => arguments[0].powf(arguments[1]),
+                    Function::Sqrt => arguments[0].sqrt(),
+                    Function::Abs => arguments[0].abs(),
+                    Function::Min => arguments[0].min(arguments[1]),
+                    Function::Max => arguments[0].max(arguments[1]),
+                }
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Operator(Operator),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(formula: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = formula.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            match number.parse::<f32>() {
+                Ok(number) => tokens.push(Token::Number(number)),
+                Err(_) => return Err(format!("invalid number {}", number)),
+            }
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+            continue;
+        }
+        match c {
+            '+' => tokens.push(Token::Operator(Operator::Add)),
+            '-' => tokens.push(Token::Operator(Operator::Subtract)),
+            '*' => tokens.push(Token::Operator(Operator::Multiply)),
+            '/' => tokens.push(Token::Operator(Operator::Divide)),
+            '^' => tokens.push(Token::Operator(Operator::Power)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            _ if c.is_whitespace() => {}
+            _ => return Err(format!("unexpected character {}", c)),
+        }
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+//Recursive descent parser, from lowest to highest precedence:
+//  sum = product (("+" | "-") product)*
+//  product = unary (("*" | "/") unary)*
+//  unary = "-" unary | power
+//  power = primary ("^" unary)?
+//  primary = number | variable | function "(" sum ("," sum)* ")" | "(" sum ")"
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(next) if *next == token => Ok(()),
+            Some(next) => Err(format!("expected {:?} but found {:?}", token, next)),
+            None => Err(format!("expected {:?} but the formula ended", token)),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_product()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Subtract))) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(self.parse_product()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_unary()?;
+        while let Some(Token::Operator(operator @ (Operator::Multiply | Operator::Divide))) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(self.parse_unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if let Some(Token::Operator(Operator::Subtract)) = self.peek() {
+            self.position += 1;
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> Result<Expression, String> {
+        let base = self.parse_primary()?;
+        if let Some(Token::Operator(Operator::Power)) = self.peek() {
+            self.position += 1;
+            //right associative, 2^3^2 is 2^(3^2)
+            return Ok(Expression::Binary(Operator::Power, Box::new(base), Box::new(self.parse_unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(*number)),
+            Some(Token::Open) => {
+                let expression = self.parse_sum()?;
+                self.expect(Token::Close)?;
+                Ok(expression)
+            }
+            Some(Token::Name(name)) => {
+                let name = name.to_owned();
+                if let Some((_, function, argument_count)) = FUNCTIONS.iter().find(|(function_name, _, _)| *function_name == name) {
+                    self.expect(Token::Open)?;
+                    let mut arguments = vec![self.parse_sum()?];
+                    while let Some(Token::Comma) = self.peek() {
+                        self.position += 1;
+                        arguments.push(self.parse_sum()?);
+                    }
+                    self.expect(Token::Close)?;
+                    if arguments.len() != *argument_count {
+                        return Err(format!("{} takes {} arguments but got {}", name, argument_count, arguments.len()));
+                    }
+                    return Ok(Expression::Call(*function, arguments));
+                }
+                match VARIABLE_NAMES.iter().find(|(variable_name, _)| *variable_name == name) {
+                    Some((_, variable)) => Ok(Expression::Variable(*variable)),
+                    None => Err(format!("unknown variable or function {}", name)),
+                }
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("the formula ended unexpectedly".to_string()),
+        }
+    }
+}
+
+fn parse_formula(formula: &str) -> Result<Expression, String> {
+    let mut parser = Parser {
+        tokens: tokenize(formula)?,
+        position: 0,
+    };
+    let expression = parser.parse_sum()?;
+    match parser.peek() {
+        Some(token) => Err(format!("unexpected {:?} after the end of the formula", token)),
+        None => Ok(expression),
+    }
+}
+
+//A way to rank files. The slug identifies it in the modes and the front end, its index in the factor list is the older numeric id
+pub struct Factor {
+    pub slug: String,
+    pub name: String,
+    pub description: String,
+    pub formula: String,
+    expression: Expression,
+}
+
+impl Factor {
+    pub fn new(slug: &str, name: &str, description: &str, formula: &str) -> Result<Factor, String> {
+        Ok(Factor {
+            slug: slug.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            formula: formula.to_string(),
+            expression: parse_formula(formula)?,
+        })
+    }
+
+    //Formulas that are undefined for a file (like 0/0) give 0 so files can still be sorted by them
+    pub fn evaluate(&self, variables: &dyn Fn(Variable) -> f32) -> f32 {
+        let value = self.expression.evaluate(variables);
+        if value.is_nan() {
+            0.0
+        } else {
+            value
+        }
+    }
+}
+
+//Format of the entries in "factors.json" (and the config file), slug defaults to the name in lowercase with "_" between the words
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FactorEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slug: Option<String>,
+    name: String,
+    #[serde(default)]
+    description: String,
+    formula: String,
+}
+
+//What the "factors" mode prints for every factor
+#[derive(Serialize)]
+struct FactorRegistryEntry<'a> {
+    index: usize,
+    slug: &'a str,
+    name: &'a str,
+    description: &'a str,
+    formula: &'a str,
+}
+
+//The factors that used to be hard coded, in their original order so the factor numbers stay the same.
+//(slug, name, description, formula)
+const DEFAULT_FACTORS: [(&str, &str, &str, &str); 37] = [
+    ("frequency", "frequency", "commits changing the file", "freq"),
+    ("fixed_bugs", "fixed bugs", "bugfix commits changing the file", "bugs"),
+    ("oldest_change", "oldest change", "lowest age of the commits changing the file", "oldest"),
+    ("newest_change", "newest change", "highest age of the commits changing the file", "newest"),
+    ("frequency_aged", "frequency aged by commit ages", "commits weighted by their age as a share of the repository age", "aged_freq"),
+    ("fixed_bugs_aged", "fixed bugs aged by commit ages", "bugfix commits weighted by their age as a share of the repository age", "aged_bugs"),
+    ("frequency_by_newest", "frequency aged by most recent newest file change", "frequency times the newest change", "freq * newest"),
+    ("fixed_bugs_by_newest", "fixed bugs aged by most recent newest file change", "fixed bugs times the newest change", "bugs * newest"),
+    ("frequency_by_oldest", "frequency aged by most recent oldest file change", "frequency times the oldest change", "freq * oldest"),
+    ("fixed_bugs_by_oldest", "fixed bugs aged by most recent oldest file change", "fixed bugs times the oldest change", "bugs * oldest"),
+    ("frequency_aged_by_newest", "frequency aged by commit ages * newest change", "aged frequency times the newest change", "newest * aged_freq"),
+    ("fixed_bugs_aged_by_newest", "fixed bugs aged by commit ages * newest change", "aged fixed bugs times the newest change", "newest * aged_bugs"),
+    ("custom", "custom formula", "age*(bugs+freq)", "newest * aged_freq + newest * aged_bugs"),
+    ("custom_freq1", "custom formula freq1", "age*(bugs+freq*2)", "newest * aged_freq * 2 + newest * aged_bugs"),
+    ("custom_freq2", "custom formula freq2", "age*(bugs+freq*10)", "newest * aged_freq * 10 + newest * aged_bugs"),
+    ("custom_bug1", "custom formula bug1", "age*(bugs*2+freq)", "newest * aged_freq + newest * aged_bugs * 2"),
+    ("custom_bug2", "custom formula bug2", "age*(bugs*10+freq)", "newest * aged_freq + newest * aged_bugs * 10"),
+    ("custom_freqonly", "custom formula freqonly", "age*(1+freq)", "newest * aged_freq + newest * 1"),
+    ("custom_bugonly", "custom formula bugonly", "age*(bugs+1)", "newest * aged_bugs + newest * 1"),
+    ("custom_newest", "custom formula more newest change", "age*(bugs+freq)+age", "newest + newest * aged_freq + newest * aged_bugs"),
+    ("custom_freq1_newest", "custom formula freq1 more newest change", "age*(bugs+freq*2)+age", "newest + newest * aged_freq * 2 + newest * aged_bugs"),
+    ("custom_freq2_newest", "custom formula freq2 more newest change", "age*(bugs+freq*10)+age", "newest + newest * aged_freq * 10 + newest * aged_bugs"),
+    ("custom_bug1_newest", "custom formula bug1 more newest change", "age*(bugs*2+freq)+age", "newest + newest * aged_freq + newest * aged_bugs * 2"),
+    ("custom_bug2_newest", "custom formula bug2 more newest change", "age*(bugs*10+freq)+age", "newest + newest * aged_freq + newest * aged_bugs * 10"),
+    ("custom_freqonly_newest", "custom formula freqonly more newest change", "age*(1+freq)+age", "newest + newest * aged_freq + newest * 1"),
+    ("custom_bugonly_newest", "custom formula bugonly more newest change", "age*(bugs+1)+age", "newest + newest * aged_bugs + newest * 1"),
+    ("frequency_decayed", "frequency decayed by half-life", "commits weighted by half-life decay, the newest counted commit weighs 1 and newer commits weigh more", "decayed_freq"),
+    ("fixed_bugs_decayed", "fixed bugs decayed by half-life", "bugfix commits weighted by half-life decay, the newest counted commit weighs 1 and newer fixes weigh more", "decayed_bugs"),
+    ("churn", "churn", "lines added and deleted", "churn"),
+    ("relative_churn", "relative churn", "churn per line of the file at its newest change", "churn / max(loc, 1)"),
+    ("bugfix_churn", "bugfix churn", "lines added and deleted by bugfix commits", "bug_churn"),
+    ("distinct_authors", "distinct authors", "authors that changed the file", "authors"),
+    ("minor_contributors", "minor contributors", "authors with less than 5% of the commits to the file", "minor_authors"),
+    ("top_owner_ratio", "top owner ratio", "share of the commits made by the author with the most", "top_owner"),
+    ("coupled_files", "coupled files", "files usually changed together with the file", "coupled_files"),
+    ("coupled_fixed_bugs", "fixed bugs in coupled files", "fixed bugs of the files usually changed together with the file", "coupled_bugs"),
+    ("bugs_introduced", "bug introducing changes", "changes to the file whose lines later bugfixes changed, needs raw data from \"szz\"", "bugs_introduced"),
+];
+
+pub fn default_factors() -> Vec<Factor> {
+    DEFAULT_FACTORS
+        .iter()
+        .map(|(slug, name, description, formula)| Factor::new(slug, name, description, formula).unwrap())
+        .collect()
+}
+
+//"Bugs per churn" -> "bugs_per_churn"
+pub fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+}
+
+//Loads the factors in path (if the file exists) on top of the default ones.
+//An entry with the slug or name of a default factor replaces it, other entries are added after the defaults.
+pub fn load_factors(path: &str) -> Result<Vec<Factor>, String> {
+    let mut factors = default_factors();
+    if !Path::new(path).exists() {
+        return Ok(factors);
+    }
+    let raw_string = std::fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+    let entries: Vec<FactorEntry> = serde_json::from_str(&raw_string).map_err(|err| format!("can not parse the factors in {}: {}", path, err))?;
+    add_factor_entries(&mut factors, entries, path)?;
+    Ok(factors)
+}
+
+//Adds the entries (from source, for the errors) to factors, replacing the factors with the same slug or name
+pub fn add_factor_entries(factors: &mut Vec<Factor>, entries: Vec<FactorEntry>, source: &str) -> Result<(), String> {
+    for entry in entries {
+        let existing_index = factors.iter().position(|existing| {
+            Some(&existing.slug) == entry.slug.as_ref() || existing.name == entry.name
+        });
+        let slug = match (&entry.slug, existing_index) {
+            (Some(slug), _) => slug.to_owned(),
+            (None, Some(index)) => factors[index].slug.to_owned(),
+            (None, None) => slugify(&entry.name),
+        };
+        if slug.is_empty() || slug.parse::<usize>().is_ok() {
+            return Err(format!("factor \"{}\" in {} needs a slug that is not a number", entry.name, source));
+        }
+        let factor = Factor::new(&slug, &entry.name, &entry.description, &entry.formula)
+            .map_err(|err| format!("invalid formula for factor \"{}\" in {}: {}", entry.name, source, err))?;
+        match existing_index {
+            Some(index) => factors[index] = factor,
+            None if factors.iter().any(|existing| existing.slug == slug) => {
+                return Err(format!("factor slug {} in {} is used twice", slug, source));
+            }
+            None => factors.push(factor),
+        }
+    }
+    Ok(())
+}
+
+//Adds factor to the factors file in path, replacing an entry with the same slug
+pub fn save_factor(path: &str, factor: &Factor) {
+    let mut entries: Vec<FactorEntry> = if Path::new(path).exists() {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    } else {
+        vec![]
+    };
+    let entry = FactorEntry {
+        slug: Some(factor.slug.to_owned()),
+        name: factor.name.to_owned(),
+        description: factor.description.to_owned(),
+        formula: factor.formula.to_owned(),
+    };
+    match entries.iter_mut().find(|existing| existing.slug.as_ref() == Some(&factor.slug)) {
+        Some(existing) => *existing = entry,
+        None => entries.push(entry),
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&entries).unwrap()).unwrap();
+}
+
+//Index of the factor with id as slug or index, the names are accepted too since the front end used to send them
+pub fn find_factor(factors: &[Factor], id: &str) -> Option<usize> {
+    if let Ok(index) = id.parse::<usize>() {
+        return if index < factors.len() { Some(index) } else { None };
+    }
+    factors
+        .iter()
+        .position(|factor| factor.slug == id)
+        .or_else(|| factors.iter().position(|factor| factor.name.trim() == id.trim()))
+}
+
+pub fn get_factor_registry_json(factors: &[Factor]) -> String {
+    let registry: Vec<FactorRegistryEntry> = factors
+        .iter()
+        .enumerate()
+        .map(|(index, factor)| FactorRegistryEntry {
+            index,
+            slug: &factor.slug,
+            name: &factor.name,
+            description: &factor.description,
+            formula: &factor.formula,
+        })
+        .collect();
+    serde_json::to_string_pretty(&registry).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //freq=3, bugs=2, oldest=4, newest=10, aged_freq=0.5, aged_bugs=0.25, everything else 0
+    fn get_variable(variable: Variable) -> f32 {
+        match variable {
+            Variable::Freq => 3.0,
+            Variable::Bugs => 2.0,
+            Variable::Oldest => 4.0,
+            Variable::Newest => 10.0,
+            Variable::AgedFreq => 0.5,
+            Variable::AgedBugs => 0.25,
+            _ => 0.0,
+        }
+    }
+
+    fn evaluate(formula: &str) -> f32 {
+        Factor::new("test", "test", "", formula).unwrap().evaluate(&get_variable)
+    }
+
+    //File::get_field before the factors were formulas, with the values of get_variable
+    fn get_old_field(n: usize) -> f32 {
+        let (freq, bugs, oldest, newest, aged_freq, aged_bugs) = (3.0, 2.0, 4.0, 10.0, 0.5, 0.25);
+        match n {
+            0 => freq,
+            1 => bugs,
+            2 => oldest,
+            3 => newest,
+            4 => aged_freq,
+            5 => aged_bugs,
+            6 => freq * newest,
+            7 => bugs * newest,
+            8 => freq * oldest,
+            9 => bugs * oldest,
+            10 => newest * aged_freq,
+            11 => newest * aged_bugs,
+            12 => newest * aged_freq + newest * aged_bugs,
+            13 => newest * aged_freq * 2.0 + newest * aged_bugs,
+            14 => newest * aged_freq * 10.0 + newest * aged_bugs,
+            15 => newest * aged_freq + newest * aged_bugs * 2.0,
+            16 => newest * aged_freq + newest * aged_bugs * 10.0,
+            17 => newest * aged_freq + newest * 1.0,
+            18 => newest * aged_bugs + newest * 1.0,
+            19 => newest + newest * aged_freq + newest * aged_bugs,
+            20 => newest + newest * aged_freq * 2.0 + newest * aged_bugs,
+            21 => newest + newest * aged_freq * 10.0 + newest * aged_bugs,
+            22 => newest + newest * aged_freq + newest * aged_bugs * 2.0,
+            23 => newest + newest * aged_freq + newest * aged_bugs * 10.0,
+            24 => newest + newest * aged_freq + newest * 1.0,
+            25 => newest + newest * aged_bugs + newest * 1.0,
+            _ => -1.0,
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(evaluate("1 + 2 * 3"), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+        assert_eq!(evaluate("10 - 4 - 3"), 3.0);
+        assert_eq!(evaluate("12 / 3 / 2"), 2.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(evaluate("2 * 3 ^ 2"), 18.0);
+        assert_eq!(evaluate("freq + bugs * newest"), 23.0);
+    }
+
+    #[test]
+    fn unary_minus_and_functions() {
+        assert_eq!(evaluate("-freq"), -3.0);
+        assert_eq!(evaluate("--freq"), 3.0);
+        assert_eq!(evaluate("2 - -1"), 3.0);
+        //the minus is applied after the power, like in math
+        assert_eq!(evaluate("-2 ^ 2"), -4.0);
+        assert_eq!(evaluate("2 ^ -1"), 0.5);
+        assert_eq!(evaluate("max(freq, bugs) + min(freq, bugs)"), 5.0);
+        assert_eq!(evaluate("pow(2, 3) + sqrt(16) + abs(-1)"), 13.0);
+        assert_eq!(evaluate("log(exp(2))"), 2.0);
+    }
+
+    #[test]
+    fn parse_errors() {
+        for formula in ["", "1 +", "(freq", "freq)", "freq bugs", "max(1)", "log(1, 2)", "foo", "foo(1)", "1..2", "freq $ 2", "max 1"] {
+            assert!(Factor::new("test", "test", "", formula).is_err(), "{} parsed", formula);
+        }
+    }
+
+    #[test]
+    fn nan_is_zero() {
+        assert_eq!(evaluate("0 / 0"), 0.0);
+        assert_eq!(evaluate("sqrt(-1)"), 0.0);
+        assert_eq!(evaluate("1 / 0"), f32::INFINITY);
+    }
+
+    #[test]
+    fn baseline_factors_match_the_old_fields() {
+        let factors = default_factors();
+        for (n, factor) in factors.iter().take(26).enumerate() {
+            let value = factor.evaluate(&get_variable);
+            let old_value = get_old_field(n);
+            assert!((value - old_value).abs() <= 1e-5 * old_value.abs(), "{}: {} != {}", factor.slug, value, old_value);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::factors::{Variable, VARIABLE_NAMES};
+use serde::Serialize;
+
+//max_age is the same for every file of a cutoff so it can not help the ranking
+pub fn get_feature_variables() -> Vec<(&'static str, Variable)> {
+    VARIABLE_NAMES
+        .iter()
+        .filter(|(name, _)| *name != "max_age")
+        .cloned()
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Model {
+    //predicts how many bugs get fixed in the file after the cutoff
+    Linear,
+    //predicts the chance that any bug gets fixed in the file after the cutoff
+    Logistic,
+}
+
+const RIDGE: f64 = 0.001;
+//features with a smaller standard deviation (relative to their mean) are constant, rounding can leave a tiny one
+const MIN_RELATIVE_DEVIATION: f64 = 1e-6;
+const LOGISTIC_ITERATIONS: usize = 300;
+const LOGISTIC_LEARNING_RATE: f64 = 0.5;
+
+//What the "fit" mode writes, the evaluation is sorted best first by the top 20% score
+#[derive(Serialize)]
+pub struct FitResult {
+    pub slug: String,
+    pub formula: String,
+    pub model: String,
+    pub train_cuttofs: Vec<usize>,
+    pub test_cuttofs: Vec<usize>,
+    pub evaluation: Vec<FitEvaluation>,
+}
+
+//Average % of the bugs found in the top 10% and 20% of the files on the test cuttofs
+#[derive(Serialize)]
+pub struct FitEvaluation {
+    pub slug: String,
+    pub top_10: f32,
+    pub top_20: f32,
+}
+
+//Weights for the unstandardized features, value = intercept + sum(weight * feature)
+pub struct FittedModel {
+    pub intercept: f64,
+    pub weights: Vec<f64>,
+}
+
+impl FittedModel {
+    //The model as a factor formula. For logistic models this is the log odds, which ranks files the same as the probability
+    pub fn to_formula(&self, feature_names: &[&str]) -> String {
+        let mut formula = format!("{}", self.intercept);
+        for (weight, name) in self.weights.iter().zip(feature_names) {
+            if *weight == 0.0 {
+                continue;
+            }
+            let sign = if *weight < 0.0 { "-" } else { "+" };
+            formula += &format!(" {} {} * {}", sign, weight.abs(), name);
+        }
+        formula
+    }
+}
+
+//Fits a model on (features, bugs fixed after the cutoff) samples.
+//Features are standardized while fitting so the learning rate and ridge work the same for every feature.
+pub fn fit(model: Model, samples: &[(Vec<f32>, f32)]) -> FittedModel {
+    let feature_count = samples.first().map(|(features, _)| features.len()).unwrap_or(0);
+    let n = samples.len().max(1) as f64;
+    let mut means = vec![0.0; feature_count];
+    let mut deviations = vec![0.0; feature_count];
+    for (features, _) in samples {
+        for (i, value) in features.iter().enumerate() {
+            means[i] += *value as f64 / n;
+        }
+    }
+    for (features, _) in samples {
+        for (i, value) in features.iter().enumerate() {
+            deviations[i] += (*value as f64 - means[i]).powi(2) / n;
+        }
+    }
+    let deviations: Vec<f64> = deviations
+        .iter()
+        .zip(&means)
+        .map(|(variance, mean)| {
+            let deviation = variance.sqrt();
+            if deviation > MIN_RELATIVE_DEVIATION * mean.abs().max(1.0) { deviation } else { 0.0 }
+        })
+        .collect();
+    //constant features get weight 0
+    let standardized: Vec<(Vec<f64>, f64)> = samples
+        .iter()
+        .map(|(features, bugs)| {
+            let row = features
+                .iter()
+                .enumerate()
+                .map(|(i, value)| if deviations[i] > 0.0 { (*value as f64 - means[i]) / deviations[i] } else { 0.0 })
+                .collect();
+            (row, *bugs as f64)
+        })
+        .collect();
+
+    let (intercept, weights) = match model {
+        Model::Linear => fit_linear(&standardized, feature_count),
+        Model::Logistic => fit_logistic(&standardized, feature_count),
+    };
+
+    //back to the scale of the raw features
+    let mut raw_intercept = intercept;
+    let mut raw_weights = vec![0.0; feature_count];
+    for i in 0..feature_count {
+        //NaN features (and so a NaN deviation) get weight 0 too, the formula has to stay valid
+        if deviations[i] > 0.0 && weights[i].is_finite() {
+            raw_weights[i] = weights[i] / deviations[i];
+            raw_intercept -= weights[i] * means[i] / deviations[i];
+        }
+    }
+    if !raw_intercept.is_finite() {
+        raw_intercept = 0.0;
+    }
+    FittedModel {
+        intercept: raw_intercept,
+        weights: raw_weights,
+    }
+}
+
+//Ridge regression, solves (X'X + ridge*n*I) w = X'y with the intercept as an unpenalized extra column
+fn fit_linear(samples: &[(Vec<f64>, f64)], feature_count: usize) -> (f64, Vec<f64>) {
+    let size = feature_count + 1;
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+    for (features, bugs) in samples {
+        let row: Vec<f64> = std::iter::once(1.0).chain(features.iter().cloned()).collect();
+        for i in 0..size {
+            for j in 0..size {
+                matrix[i][j] += row[i] * row[j];
+            }
+            matrix[i][size] += row[i] * bugs;
+        }
+    }
+    for (i, matrix_row) in matrix.iter_mut().enumerate().skip(1) {
+        matrix_row[i] += RIDGE * samples.len() as f64;
+    }
+    let solution = solve(matrix);
+    (solution[0], solution[1..].to_vec())
+}
+
+//Gaussian elimination with partial pivoting on an augmented matrix
+fn solve(mut matrix: Vec<Vec<f64>>) -> Vec<f64> {
+    let size = matrix.len();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))
+            .unwrap();
+        matrix.swap(column, pivot);
+        if matrix[column][column].abs() < 1e-12 {
+            continue;
+        }
+        let pivot_row = matrix[column].clone();
+        for (row, matrix_row) in matrix.iter_mut().enumerate() {
+            if row != column {
+                let factor = matrix_row[column] / pivot_row[column];
+                for (value, pivot_value) in matrix_row.iter_mut().zip(&pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    (0..size)
+        .map(|i| if matrix[i][i].abs() < 1e-12 { 0.0 } else { matrix[i][size] / matrix[i][i] })
+        .collect()
+}
+
+//Batch gradient descent on the log loss with an l2 penalty, a file is positive if any bug got fixed in it after the cutoff
+fn fit_logistic(samples: &[(Vec<f64>, f64)], feature_count: usize) -> (f64, Vec<f64>) {
+    let n = samples.len().max(1) as f64;
+    let mut intercept = 0.0;
+    let mut weights = vec![0.0; feature_count];
+    for _ in 0..LOGISTIC_ITERATIONS {
+        let mut intercept_gradient = 0.0;
+        let mut gradients = vec![0.0; feature_count];
+        for (features, bugs) in samples {
+            let label = if *bugs > 0.0 { 1.0 } else { 0.0 };
+            let logit = intercept + features.iter().zip(&weights).map(|(x, w)| x * w).sum::<f64>();
+            let error = 1.0 / (1.0 + (-logit).exp()) - label;
+            intercept_gradient += error / n;
+            for (gradient, x) in gradients.iter_mut().zip(features) {
+                *gradient += error * x / n;
+            }
+        }
+        intercept -= LOGISTIC_LEARNING_RATE * intercept_gradient;
+        for (weight, gradient) in weights.iter_mut().zip(&gradients) {
+            *weight -= LOGISTIC_LEARNING_RATE * (gradient + RIDGE * *weight);
+        }
+    }
+    (intercept, weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factors::Factor;
+
+    //the first feature is constant, 0.1 is not exact in binary so its computed variance is not quite 0
+    fn get_samples() -> Vec<(Vec<f32>, f32)> {
+        (0..50).map(|i| (vec![0.1, i as f32, (i % 7) as f32], if i % 3 == 0 { 2.0 * i as f32 } else { 0.0 })).collect()
+    }
+
+    #[test]
+    fn constant_features_get_weight_zero() {
+        for model in [Model::Linear, Model::Logistic] {
+            let fitted_model = fit(model, &get_samples());
+            assert_eq!(fitted_model.weights[0], 0.0);
+            assert!(fitted_model.intercept.is_finite());
+            assert!(fitted_model.weights.iter().all(|weight| weight.is_finite()));
+            let formula = fitted_model.to_formula(&["loc", "freq", "bugs"]);
+            assert!(Factor::new("fitted", "fitted", "", &formula).is_ok(), "{}", formula);
+        }
+    }
+
+    #[test]
+    fn linear_fit_recovers_a_linear_relation() {
+        let samples: Vec<(Vec<f32>, f32)> = (0..20).map(|i| (vec![i as f32, 5.0], 3.0 * i as f32 + 1.0)).collect();
+        let fitted_model = fit(Model::Linear, &samples);
+        assert!((fitted_model.weights[0] - 3.0).abs() < 0.01, "{}", fitted_model.weights[0]);
+        assert!((fitted_model.intercept - 1.0).abs() < 0.2, "{}", fitted_model.intercept);
+        assert_eq!(fitted_model.weights[1], 0.0);
+    }
+
+    #[test]
+    fn nan_features_get_weight_zero() {
+        let samples: Vec<(Vec<f32>, f32)> = (0..20).map(|i| (vec![i as f32, if i == 3 { f32::NAN } else { 1.0 }], i as f32)).collect();
+        let fitted_model = fit(Model::Linear, &samples);
+        assert_eq!(fitted_model.weights[1], 0.0);
+        assert!(fitted_model.intercept.is_finite());
+    }
+}
@@ -0,0 +1,433 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+//How many lines after a signature we look for the opening brace before treating it as a bodyless declaration
+const MAX_SIGNATURE_LINES: usize = 10;
+
+//Words that can sit in the "type" or "name" position of a c-like signature regex without it being a function
+const C_LIKE_KEYWORDS: [&str; 16] = [
+    "if", "else", "for", "while", "do", "switch", "case", "catch", "return", "new", "delete",
+    "throw", "sizeof", "typeof", "using", "goto",
+];
+
+//Regex based function extractor for a single language, chosen by the file extension.
+//The regex should have a "name" group with the function name, only the built in javascript regex has none,
+//its whole match is cleaned up the way the original code did.
+//indentation_blocks is for languages where the function body ends by indentation (python) instead of braces.
+pub struct FunctionExtractor {
+    pub extensions: Vec<String>,
+    regex: Regex,
+    indentation_blocks: bool,
+}
+
+//Line range (1-based, inclusive) of a function in a file
+pub struct FunctionSpan {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+//Format of the entries in "regex_function_extractors.json"
+#[derive(Deserialize)]
+struct FunctionExtractorEntry {
+    extensions: Vec<String>,
+    pattern: String,
+    #[serde(default)]
+    indentation_blocks: bool,
+}
+
+impl FunctionExtractor {
+    pub fn new(extensions: &[&str], pattern: &str, indentation_blocks: bool) -> FunctionExtractor {
+        FunctionExtractor {
+            extensions: extensions.iter().map(|e| e.to_lowercase()).collect(),
+            regex: Regex::new(pattern).unwrap(),
+            indentation_blocks,
+        }
+    }
+
+    //Returns the function name if the line contains a function signature
+    pub fn get_function_name(&self, line: &str) -> Option<String> {
+        let captures = self.regex.captures(line)?;
+        for group in ["name", "type"] {
+            if let Some(words) = captures.name(group) {
+                if words.as_str().split_whitespace().any(|word| C_LIKE_KEYWORDS.contains(&word)) {
+                    return None;
+                }
+            }
+        }
+        match captures.name("name") {
+            Some(name) => Some(name.as_str().to_string()),
+            None => Some(clean_js_function_name(captures.get(0).unwrap().as_str())),
+        }
+    }
+
+    //Finds all functions in a whole file and the lines they cover
+    pub fn get_function_spans(&self, content: &str) -> Vec<FunctionSpan> {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut spans = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            if let Some(name) = self.get_function_name(line) {
+                let end = if self.indentation_blocks {
+                    indentation_block_end(&lines, i)
+                } else {
+                    brace_block_end(&lines, i)
+                };
+                spans.push(FunctionSpan {
+                    name,
+                    start: i + 1,
+                    end: end + 1,
+                });
+            }
+        }
+        spans
+    }
+
+    fn handles_extension(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|e| e == extension)
+    }
+}
+
+//Index of the line where the brace block opened at or after start is closed again.
+//Braces in strings, character literals and comments are skipped. "..." and '...' end at the end of the line, `...` and /* */ do not.
+//A ' only starts a literal when it is closed on the same line, so rust lifetimes are left alone.
+fn brace_block_end(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0;
+    let mut opened = false;
+    let mut in_block_comment = false;
+    let mut quote: Option<char> = None;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut j = 0;
+        while j < chars.len() {
+            let next = chars.get(j + 1).copied();
+            if in_block_comment {
+                if chars[j] == '*' && next == Some('/') {
+                    in_block_comment = false;
+                    j += 1;
+                }
+            } else if let Some(open_quote) = quote {
+                if chars[j] == '\\' {
+                    j += 1;
+                } else if chars[j] == open_quote {
+                    quote = None;
+                }
+            } else {
+                match chars[j] {
+                    '/' if next == Some('/') => break,
+                    '/' if next == Some('*') => {
+                        in_block_comment = true;
+                        j += 1;
+                    }
+                    '"' | '`' => quote = Some(chars[j]),
+                    '\'' if chars[j + 1..].contains(&'\'') => quote = Some('\''),
+                    '{' => {
+                        depth += 1;
+                        opened = true;
+                    }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                if opened && depth <= 0 {
+                    return i;
+                }
+            }
+            j += 1;
+        }
+        if quote != Some('`') {
+            quote = None;
+        }
+        //declarations and one line arrow functions have no body to look for
+        if !opened && line.trim_end().ends_with(';') {
+            return i;
+        }
+        if !opened && i >= start + MAX_SIGNATURE_LINES {
+            return start;
+        }
+    }
+    lines.len() - 1
+}
+
+//Index of the last line indented deeper than the line at start, blank lines do not end the block
+fn indentation_block_end(lines: &[&str], start: usize) -> usize {
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let start_indentation = indentation(lines[start]);
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) <= start_indentation {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+//Name of the innermost function covering line_number, if any
+pub fn get_enclosing_function(spans: &[FunctionSpan], line_number: usize) -> Option<&str> {
+    spans
+        .iter()
+        .filter(|span| span.start <= line_number && line_number <= span.end)
+        .max_by_key(|span| span.start)
+        .map(|span| span.name.as_str())
+}
+
+//The name in a match of the original javascript only regex, "function name(a, b)", "name = (a) =>" or "name = async (a) =>"
+fn clean_js_function_name(matched: &str) -> String {
+    let head = matched.split('(').next().unwrap();
+    match head.split_once('=') {
+        Some((name, _)) => name.trim().to_string(),
+        None => head.trim().trim_start_matches("function").trim().to_string(),
+    }
+}
+
+//Built in extractors, the javascript one is the original regex of get_functions_from_diff
+pub fn default_function_extractors() -> Vec<FunctionExtractor> {
+    vec![
+        //javascript/typescript
+        FunctionExtractor::new(
+            &["js", "jsx", "mjs", "cjs", "ts", "tsx", "vue", "svelte", "html", "htm"],
+            r"function\s+[a-zA-Z0-9_]+\(+[a-zA-Z0-9_:, ]*\)|[a-zA-Z0-9]+\s*=\s*\([a-zA-Z0-9: ]*\)\s*=>|[a-zA-Z0-9]+\s*=\s*async\s*\([a-zA-Z0-9: ]*\)\s*=>",
+            false,
+        ),
+        //rust
+        FunctionExtractor::new(
+            &["rs"],
+            r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:default\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+"[^"]*"\s+)?fn\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)"#,
+            false,
+        ),
+        //python
+        FunctionExtractor::new(
+            &["py", "pyw"],
+            r"^\s*(?:async\s+)?def\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)\s*\(",
+            true,
+        ),
+        //go
+        FunctionExtractor::new(
+            &["go"],
+            r"^\s*func\s+(?:\([^)]*\)\s*)?(?P<name>[A-Za-z_][A-Za-z0-9_]*)\s*(?:\[[^\]]*\])?\s*\(",
+            false,
+        ),
+        //java and c# methods
+        FunctionExtractor::new(
+            &["java", "cs"],
+            r"^\s*(?:(?:public|private|protected|internal|static|final|abstract|synchronized|native|virtual|override|sealed|async|extern|unsafe|partial|default|strictfp)\s+)*(?:<[^>]+>\s+)?(?P<type>[A-Za-z_][\w<>\[\],.?]*)\s+(?P<name>[A-Za-z_]\w*)\s*\([^;]*$",
+            false,
+        ),
+        //c/c++ function definitions
+        FunctionExtractor::new(
+            &["c", "h", "cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx", "inl"],
+            r"^\s*(?:(?:static|inline|extern|virtual|explicit|constexpr|friend)\s+)*(?P<type>[A-Za-z_][\w:<>,]*(?:\s+[A-Za-z_][\w:<>,]*)*)[\s\*&]+(?P<name>[A-Za-z_~][\w:~]*)\s*\([^;]*$",
+            false,
+        ),
+    ]
+}
+
+//Loads user extractors from path (if the file exists) in front of the built in ones, so they take priority.
+//Err is a message for the user, for a file that is no list of entries or a pattern that is invalid or has no "name" group
+pub fn load_function_extractors(path: &str) -> Result<Vec<FunctionExtractor>, String> {
+    let mut extractors = vec![];
+    if Path::new(path).exists() {
+        let raw_string = std::fs::read_to_string(path).map_err(|err| format!("can not read {}: {}", path, err))?;
+        let entries: Vec<FunctionExtractorEntry> =
+            serde_json::from_str(&raw_string).map_err(|err| format!("invalid function extractors {}: {}", path, err))?;
+        for entry in entries {
+            let regex = Regex::new(&entry.pattern).map_err(|err| format!("invalid regex {} in {}: {}", entry.pattern, path, err))?;
+            if !regex.capture_names().any(|name| name == Some("name")) {
+                return Err(format!("the pattern {} in {} needs a \"name\" group, like (?P<name>[A-Za-z_]\\w*)", entry.pattern, path));
+            }
+            extractors.push(FunctionExtractor {
+                extensions: entry.extensions.iter().map(|e| e.to_lowercase()).collect(),
+                regex,
+                indentation_blocks: entry.indentation_blocks,
+            });
+        }
+    }
+    extractors.extend(default_function_extractors());
+    Ok(extractors)
+}
+
+//Picks the first extractor handling the extension of filename, None means functions are not extracted for it
+pub fn get_extractor_for_file<'a>(
+    extractors: &'a [FunctionExtractor],
+    filename: &str,
+) -> Option<&'a FunctionExtractor> {
+    let basename = filename.rsplit('/').next().unwrap_or(filename);
+    let extension = basename.rsplit_once('.')?.1.to_lowercase();
+    extractors.iter().find(|e| e.handles_extension(&extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //(name, start, end) of the functions the built in extractor for extension finds in the lines
+    fn get_spans(extension: &str, lines: &[&str]) -> Vec<(String, usize, usize)> {
+        let extractors = default_function_extractors();
+        let extractor = get_extractor_for_file(&extractors, &format!("src/file.{}", extension)).unwrap();
+        extractor
+            .get_function_spans(&lines.join("\n"))
+            .into_iter()
+            .map(|span| (span.name, span.start, span.end))
+            .collect()
+    }
+
+    fn span(name: &str, start: usize, end: usize) -> (String, usize, usize) {
+        (name.to_string(), start, end)
+    }
+
+    #[test]
+    fn javascript() {
+        let lines = [
+            "function outer(a, b) {",
+            "  const s = \"}\";",
+            "  if (a) {",
+            "    return b;",
+            "  }",
+            "}",
+            "const add = (x) => x + 1;",
+            "const load = async (url) => {",
+            "  return `${url} }`;",
+            "};",
+        ];
+        assert_eq!(get_spans("js", &lines), [span("outer", 1, 6), span("add", 7, 7), span("load", 8, 10)]);
+    }
+
+    #[test]
+    fn rust() {
+        let lines = [
+            "pub fn parse(input: &str) -> Option<char> {",
+            "    // a } in a comment",
+            "    let open = '{';",
+            "    /* and } in",
+            "       a block comment */",
+            "    input.chars().find(|c| *c == open)",
+            "}",
+            "",
+            "pub(crate) async fn lifetimes<'a>(x: &'a str) -> &'a str {",
+            "    x",
+            "}",
+        ];
+        assert_eq!(get_spans("rs", &lines), [span("parse", 1, 7), span("lifetimes", 9, 11)]);
+    }
+
+    #[test]
+    fn python() {
+        let lines = [
+            "class Parser:",
+            "    def parse(self, text):",
+            "        if text:",
+            "",
+            "            return text.strip()",
+            "        return None",
+            "",
+            "    async def close(self):",
+            "        pass",
+            "x = 1",
+        ];
+        assert_eq!(get_spans("py", &lines), [span("parse", 2, 6), span("close", 8, 9)]);
+    }
+
+    #[test]
+    fn go() {
+        let lines = [
+            "func (s *Server) Handle(w Writer) {",
+            "\tfmt.Println(\"{\")",
+            "}",
+            "",
+            "func Map[T any](xs []T) []T {",
+            "\treturn xs",
+            "}",
+        ];
+        assert_eq!(get_spans("go", &lines), [span("Handle", 1, 3), span("Map", 5, 7)]);
+    }
+
+    #[test]
+    fn java_and_c_sharp() {
+        let lines = [
+            "public class Names {",
+            "    public static List<String> names(int count) {",
+            "        for (int i = 0; i < count; i++) {",
+            "            log(\"}\");",
+            "        }",
+            "        return null;",
+            "    }",
+            "    abstract void run();",
+            "}",
+        ];
+        assert_eq!(get_spans("java", &lines), [span("names", 2, 7)]);
+        assert_eq!(get_spans("cs", &lines), [span("names", 2, 7)]);
+    }
+
+    #[test]
+    fn c_and_cpp() {
+        let lines = [
+            "static int *parse(const char *s)",
+            "{",
+            "    if (s[0] == '}') {",
+            "        return 0;",
+            "    }",
+            "    return 1;",
+            "}",
+            "int declared(void);",
+        ];
+        assert_eq!(get_spans("c", &lines), [span("parse", 1, 7)]);
+        assert_eq!(get_spans("cpp", &lines), [span("parse", 1, 7)]);
+    }
+
+    #[test]
+    fn enclosing_functions() {
+        let lines = [
+            "function outer() {",
+            "  function inner() {",
+            "    return 1;",
+            "  }",
+            "  return 2;",
+            "}",
+            "outer();",
+        ];
+        let extractors = default_function_extractors();
+        let spans = get_extractor_for_file(&extractors, "a.js").unwrap().get_function_spans(&lines.join("\n"));
+        assert_eq!(get_enclosing_function(&spans, 1), Some("outer"));
+        assert_eq!(get_enclosing_function(&spans, 3), Some("inner"));
+        assert_eq!(get_enclosing_function(&spans, 4), Some("inner"));
+        assert_eq!(get_enclosing_function(&spans, 5), Some("outer"));
+        assert_eq!(get_enclosing_function(&spans, 7), None);
+        assert!(get_extractor_for_file(&extractors, "README.md").is_none());
+        assert!(get_extractor_for_file(&extractors, "Makefile").is_none());
+    }
+
+    fn load_from_json(name: &str, json: &str) -> Result<Vec<FunctionExtractor>, String> {
+        let path = std::env::temp_dir().join(format!("gitdiffjson_extractors_{}_{}.json", name, std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let extractors = load_function_extractors(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        extractors
+    }
+
+    #[test]
+    fn user_extractors_override_the_built_in_ones() {
+        let extractors = load_from_json("override", r#"[{"extensions": ["PY"], "pattern": "^\\s*def\\s+(?P<name>\\w+)\\s*\\(.*:\\s*$"}]"#).unwrap();
+        let extractor = get_extractor_for_file(&extractors, "tool.py").unwrap();
+        assert_eq!(extractor.get_function_name("def run(args):"), Some("run".to_string()));
+        //the built in python regex takes it, the user one does not
+        assert_eq!(extractor.get_function_name("def run(args): return 1"), None);
+        //the user entry has no indentation_blocks, so it looks for braces
+        assert!(!extractor.indentation_blocks);
+        assert!(get_extractor_for_file(&extractors, "main.rs").is_some());
+        assert_eq!(extractors.len(), default_function_extractors().len() + 1);
+    }
+
+    #[test]
+    fn user_patterns_need_a_name_group() {
+        let error = load_from_json("unnamed", r#"[{"extensions": ["kt"], "pattern": "^\\s*fun\\s+\\w+"}]"#).err().unwrap();
+        assert!(error.contains("needs a \"name\" group"), "{}", error);
+        let error = load_from_json("invalid", r#"[{"extensions": ["kt"], "pattern": "(?P<name>"}]"#).err().unwrap();
+        assert!(error.starts_with("invalid regex"), "{}", error);
+        let error = load_from_json("not_a_list", r#"{"extensions": ["kt"]}"#).err().unwrap();
+        assert!(error.starts_with("invalid function extractors"), "{}", error);
+        assert_eq!(load_from_json("empty", "[]").unwrap().len(), default_function_extractors().len());
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+
+//Where the history is split for an evaluation, in % of the repository age (0 is the newest commit, like the ages).
+//Factors are counted from the commits in [train_start, cuttof] and bugs fixed in (cuttof, test_end] are what they should find.
+//A plain cuttof is the window [0, cuttof] -> (cuttof, 100].
+#[derive(Serialize, Clone, Copy)]
+pub struct EvaluationWindow {
+    pub train_start: usize,
+    pub cuttof: usize,
+    pub test_end: usize,
+    //only the bugs fixed this far past the cuttof count, so every cuttof is measured on the same amount of history
+    pub horizon: Option<Horizon>,
+}
+
+//How much history after the cuttof the bugs fixed are counted in
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Horizon {
+    //the next commits that changed files
+    Commits(usize),
+    Days(usize),
+}
+
+impl EvaluationWindow {
+    pub fn from_cuttof(cuttof: usize) -> EvaluationWindow {
+        EvaluationWindow {
+            train_start: 0,
+            cuttof,
+            test_end: 100,
+            horizon: None,
+        }
+    }
+
+    //true when it is a plain cuttof, from the newest commit to the oldest
+    pub fn is_cuttof(&self) -> bool {
+        self.train_start == 0 && self.test_end == 100 && self.horizon.is_none()
+    }
+}
+
+impl fmt::Display for EvaluationWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.train_start == 0 && self.test_end == 100 {
+            write!(f, "{}%", self.cuttof)?;
+        } else {
+            write!(f, "{}-{}% -> {}%", self.train_start, self.cuttof, self.test_end)?;
+        }
+        match self.horizon {
+            Some(Horizon::Commits(commits)) => write!(f, " (next {} commits)", commits),
+            Some(Horizon::Days(days)) => write!(f, " (next {} days)", days),
+            None => Ok(()),
+        }
+    }
+}
+
+//How far down the ranked files the bugs found are counted, a % of the files or a number of files
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Breakpoint {
+    Precentage(f64),
+    Count(usize),
+}
+
+impl Breakpoint {
+    //Index of the file in the ranking where the bugs found are read, None when there are not enough files.
+    //Precentages keep the index results have always been measured at (1% of 200 files is read after the 3rd file), counts are exact.
+    pub fn get_file_index(&self, file_count: usize) -> Option<usize> {
+        let index = match self {
+            Breakpoint::Precentage(precentage) => (file_count as f64 * precentage / 100.0) as usize,
+            Breakpoint::Count(count) => count.checked_sub(1)?,
+        };
+        if index < file_count {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    //% of the files looked at by the breakpoint, what a random order would find of the bugs
+    pub fn get_share_of_files(&self, file_count: usize) -> f64 {
+        match self {
+            Breakpoint::Precentage(precentage) => *precentage,
+            Breakpoint::Count(count) => *count as f64 / file_count as f64 * 100.0,
+        }
+    }
+}
+
+//"1" for 1% and "10 files" for a count, the text reports write the breakpoints like this
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Precentage(precentage) => write!(f, "{}", precentage),
+            Breakpoint::Count(count) => write!(f, "{} files", count),
+        }
+    }
+}
+
+//The windows and breakpoints an analysis is run on
+pub struct EvaluationGrid {
+    pub windows: Vec<EvaluationWindow>,
+    pub breakpoints: Vec<Breakpoint>,
+    //the horizon every window has
+    pub horizon: Option<Horizon>,
+}
+
+//What a "grid=<path>" file (or the grid of the config file) can have, every field is optional
+#[derive(Deserialize, Default, Clone)]
+pub struct GridFile {
+    //"cuttofs" in older grid files
+    #[serde(alias = "cuttofs")]
+    cutoffs: Option<Vec<usize>>,
+    //[train_start, cutoff, test_end]
+    windows: Option<Vec<[usize; 3]>>,
+    breakpoints: Option<Vec<f64>>,
+    top_n: Option<Vec<usize>>,
+    horizon: Option<usize>,
+    horizon_days: Option<usize>,
+}
+
+impl GridFile {
+    //The same grid as "cutoffs=", "windows=", "breakpoints=", "top_n=", "horizon=" and "horizon_days=" args
+    pub fn to_args(&self) -> Vec<String> {
+        let join = |values: Vec<String>| values.join(",");
+        let mut args = vec![];
+        if let Some(cutoffs) = &self.cutoffs {
+            args.push(format!("cutoffs={}", join(cutoffs.iter().map(|cutoff| cutoff.to_string()).collect())));
+        }
+        if let Some(windows) = &self.windows {
+            let windows = windows
+                .iter()
+                .map(|[train_start, cuttof, test_end]| format!("{}:{}:{}", train_start, cuttof, test_end))
+                .collect();
+            args.push(format!("windows={}", join(windows)));
+        }
+        if let Some(breakpoints) = &self.breakpoints {
+            args.push(format!("breakpoints={}", join(breakpoints.iter().map(|breakpoint| breakpoint.to_string()).collect())));
+        }
+        if let Some(top_n) = &self.top_n {
+            args.push(format!("top_n={}", join(top_n.iter().map(|count| count.to_string()).collect())));
+        }
+        if let Some(horizon) = self.horizon {
+            args.push(format!("horizon={}", horizon));
+        }
+        if let Some(horizon_days) = self.horizon_days {
+            args.push(format!("horizon_days={}", horizon_days));
+        }
+        args
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(key: &str, value: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| item.trim().parse::<T>().map_err(|_| format!("{} has {} which is not a number", key, item)))
+        .collect()
+}
+
+//"0:40:60" -> [0, 40] -> (40, 60]
+fn parse_window(value: &str) -> Result<[usize; 3], String> {
+    let parts: Vec<usize> = parse_list("windows", &value.replace(':', ","))?;
+    match parts[..] {
+        [train_start, cuttof, test_end] => Ok([train_start, cuttof, test_end]),
+        _ => Err(format!("window {} is not train_start:cutoff:test_end", value)),
+    }
+}
+
+fn to_window([train_start, cuttof, test_end]: [usize; 3]) -> Result<EvaluationWindow, String> {
+    if !(train_start <= cuttof && cuttof < test_end && test_end <= 100) {
+        return Err(format!("window {}:{}:{} needs train_start <= cutoff < test_end <= 100", train_start, cuttof, test_end));
+    }
+    Ok(EvaluationWindow {
+        train_start,
+        cuttof,
+        test_end,
+        horizon: None,
+    })
+}
+
+//Parses "grid=<path>" (a json file with any of "cutoffs", "windows", "breakpoints", "top_n", "horizon" and "horizon_days"), then "cutoffs=5,10",
+//"windows=0:40:60,20:60:80", "breakpoints=0.1,0.5,1" (% of the files), "top_n=10,50" (numbers of files), "horizon=500" (commits)
+//and "horizon_days=90" args, which override the file. Breakpoints and top_n are combined. Anything not given keeps the defaults, other args are ignored.
+//horizon_days also needs "ages=days" or "ages=author_days" in args.
+//Err is a message for the user, the args come from the flags and the config file
+pub fn parse_evaluation_grid(args: &[String], default_cuttofs: &[usize], default_breakpoints: &[f64]) -> Result<EvaluationGrid, String> {
+    let mut grid = GridFile::default();
+    for arg in args {
+        if let Some(("grid", path)) = arg.split_once('=') {
+            let file_string = fs::read_to_string(path).map_err(|err| format!("can not read the grid {}: {}", path, err))?;
+            grid = serde_json::from_str(&file_string).map_err(|err| format!("invalid grid {}: {}", path, err))?;
+        }
+    }
+    for arg in args {
+        match arg.split_once('=') {
+            //"cuttofs" is the older spelling
+            Some(("cutoffs", value)) | Some(("cuttofs", value)) => grid.cutoffs = Some(parse_list("cutoffs", value)?),
+            Some(("windows", value)) => grid.windows = Some(value.split(',').map(parse_window).collect::<Result<_, String>>()?),
+            Some(("breakpoints", value)) => grid.breakpoints = Some(parse_list("breakpoints", value)?),
+            Some(("top_n", value)) => grid.top_n = Some(parse_list("top_n", value)?),
+            Some(("horizon", value)) => grid.horizon = parse_list("horizon", value)?.first().cloned(),
+            Some(("horizon_days", value)) => grid.horizon_days = parse_list("horizon_days", value)?.first().cloned(),
+            _ => {}
+        }
+    }
+    let day_ages = args.iter().rev().find_map(|arg| arg.strip_prefix("ages=")).is_some_and(|ages| ages == "days" || ages == "author_days");
+    if grid.horizon_days.is_some() && !day_ages {
+        return Err("horizon_days needs day ages, add --ages days or --ages author_days".to_string());
+    }
+    let horizon = match (grid.horizon, grid.horizon_days) {
+        (Some(_), Some(_)) => return Err("use horizon (commits) or horizon_days, not both".to_string()),
+        (Some(0), _) | (_, Some(0)) => return Err("the horizon has to be at least 1".to_string()),
+        (Some(commits), None) => Some(Horizon::Commits(commits)),
+        (None, Some(days)) => Some(Horizon::Days(days)),
+        (None, None) => None,
+    };
+
+    let mut windows: Vec<EvaluationWindow> = grid
+        .cutoffs
+        .unwrap_or_default()
+        .into_iter()
+        .map(|cuttof| to_window([0, cuttof, 100]))
+        .chain(grid.windows.unwrap_or_default().into_iter().map(to_window))
+        .collect::<Result<_, String>>()?;
+    if windows.is_empty() {
+        windows = default_cuttofs.iter().map(|cuttof| EvaluationWindow::from_cuttof(*cuttof)).collect();
+    }
+
+    //top_n alone replaces the default precentages
+    let mut breakpoints: Vec<Breakpoint> = match (&grid.breakpoints, &grid.top_n) {
+        (None, Some(_)) => vec![],
+        (Some(precentages), _) => precentages.iter().map(|precentage| Breakpoint::Precentage(*precentage)).collect(),
+        (None, None) => default_breakpoints.iter().map(|precentage| Breakpoint::Precentage(*precentage)).collect(),
+    };
+    breakpoints.extend(grid.top_n.unwrap_or_default().into_iter().map(Breakpoint::Count));
+    for window in &mut windows {
+        window.horizon = horizon;
+    }
+
+    Ok(EvaluationGrid {
+        windows,
+        breakpoints,
+        horizon,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horizon_days_needs_day_ages() {
+        assert!(parse_evaluation_grid(&["horizon_days=30".to_string()], &[50], &[10.0]).is_err());
+        assert!(parse_evaluation_grid(&["horizon_days=30".to_string(), "ages=position".to_string()], &[50], &[10.0]).is_err());
+        let grid = parse_evaluation_grid(&["ages=days".to_string(), "horizon_days=30".to_string()], &[50], &[10.0]).unwrap();
+        assert!(matches!(grid.horizon, Some(Horizon::Days(30))));
+    }
+}
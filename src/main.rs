@@ -1,54 +1,230 @@
 #![feature(drain_filter)]
 
-use git2::{Oid, Repository, RepositoryOpenFlags};
+mod aggregate;
+mod bugfix;
+mod bugfix_report;
+mod cli;
+mod co_change;
+mod config;
+mod factors;
+mod fit;
+mod function_extractors;
+mod grid;
+mod metrics;
+mod raw_data;
+mod report;
+mod stats;
+mod szz;
+
+use aggregate::{aggregate, get_repo_names, read_result_file, RepoResult};
+use bugfix::{parse_bugfix_classifier, BugfixClassifier};
+use bugfix_report::{get_bugfix_quality, read_labels};
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Command, DEFAULT_RAW_DATA};
+use co_change::{CoChange, MIN_CONFIDENCE, MIN_SUPPORT};
+use config::Config;
+use factors::{add_factor_entries, find_factor, get_factor_registry_json, load_factors, save_factor, Factor, Variable};
+use fit::{fit, get_feature_variables, FitEvaluation, FitResult, Model};
+use function_extractors::{
+    get_enclosing_function, get_extractor_for_file, load_function_extractors, FunctionExtractor,
+    FunctionSpan,
+};
+use grid::{parse_evaluation_grid, Breakpoint, EvaluationGrid, EvaluationWindow, Horizon};
+use metrics::{Metrics, RankedFile};
+use git2::{DiffFindOptions, Oid, Repository, RepositoryOpenFlags, Sort, Tree};
 use indicatif::{ProgressBar, ProgressStyle};
 
+use report::{parse_output_format, CuttofAnalysis, FactorAnalysis, FactorBreakpoints, MultiAnalysisReport, OutputFormat, TextReport};
+use stats::compare_factors;
+use szz::{check_ground_truth, count_known_fixes, count_known_function_fixes, find_bug_introducing_commits, parse_ground_truth, GroundTruth};
+use raw_data::{read_raw_data, write_raw_data, CommitRecord, FileChange, RawData, RenameMap};
 use rayon::{prelude::*};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use core::time;
-use std::collections::{HashMap, VecDeque, HashSet};
+use std::collections::{HashMap, HashSet};
 use std::ffi::{OsString};
 use std::fmt::Write as _;
 
 use std::fs::OpenOptions;
 use std::io::Write as _;
-use std::process::Command;
 
 use std::sync::{Arc, Mutex};
-use std::{env, fmt, fs};
+use std::{fmt, fs};
+
+
+//What a merge commit counts as changing
+#[derive(PartialEq)]
+enum MergePolicy {
+    //merges get no files at all, the changes are counted in the merged commits
+    Skip,
+    //only what the merge changed compared to the branch it was merged into
+    FirstParent,
+    //like git's combined diff, only the files that differ from every parent (the ones the merge itself changed)
+    AllParents,
+}
 
+//Options for how "repo" modes walk the history, key=value options from the flags and the config
+struct RepoOptions {
+    start_ref: String,
+    order: Sort,
+    first_parent: bool,
+    merge_policy: MergePolicy,
+    //resolve authors through the repositorys .mailmap so one person with several names/emails is one author
+    mailmap: bool,
+    //find the bug introducing commits of the bugfixes too (see szz)
+    szz: bool,
+}
+impl RepoOptions {
+    fn new() -> RepoOptions {
+        RepoOptions {
+            start_ref: "HEAD".to_string(),
+            //same order as `git log`, which is what ages were based on before
+            order: Sort::NONE,
+            first_parent: false,
+            merge_policy: MergePolicy::FirstParent,
+            mailmap: false,
+            szz: false,
+        }
+    }
+}
 
-fn get_implemented_nr_of_fields_for_analysis() -> i32 {
-    //TODO: this needs to be manualy updated when adding fields.
-    return 26;
+//Parses "ref=<name>", "order=git|time|topo|topo_time", "first_parent=true", "merges=skip|first_parent|all_parents", "mailmap=true" and "szz=true" args, other args are ignored
+//Err is a message for the user, the args come from the flags and the config file
+fn parse_repo_options(args: &[String]) -> Result<RepoOptions, String> {
+    let mut options = RepoOptions::new();
+    for arg in args {
+        let (key, value) = match arg.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        match key {
+            "ref" => options.start_ref = value.to_string(),
+            "order" => {
+                options.order = match value {
+                    "git" => Sort::NONE,
+                    "time" => Sort::TIME,
+                    "topo" => Sort::TOPOLOGICAL,
+                    "topo_time" => Sort::TOPOLOGICAL | Sort::TIME,
+                    _ => return Err(format!("unknown order {}, use git, time, topo or topo_time", value)),
+                }
+            }
+            "first_parent" => options.first_parent = parse_bool(key, value)?,
+            "mailmap" => options.mailmap = parse_bool(key, value)?,
+            "szz" => options.szz = parse_bool(key, value)?,
+            //what szz counts as bugfixes, see parse_bugfix_classifier
+            "bugfix" | "fix_types" | "fix_scopes" | "issues" | "bug_types" => {}
+            "merges" => {
+                options.merge_policy = match value {
+                    "skip" => MergePolicy::Skip,
+                    "first_parent" => MergePolicy::FirstParent,
+                    "all_parents" => MergePolicy::AllParents,
+                    _ => return Err(format!("unknown merge policy {}, use skip, first_parent or all_parents", value)),
+                }
+            }
+            _ => return Err(format!("unknown repo option {}", key)),
+        }
+    }
+    Ok(options)
 }
 
-//This generates a hashmap containing the relevant data for analysis from a local repo
-fn generate_json(repo_path: &str) -> HashMap<String, Vec<(String, Vec<String>, i32, String)>> {
-    let sha_to_parsed_diffs: HashMap<String, Vec<(String, Vec<String>, i32, String)>> =
-        HashMap::new();
-    let shared_data = Arc::new(Mutex::new(sha_to_parsed_diffs));
-
-    let output = Command::new("git")
-        .arg("--git-dir=".to_owned() + repo_path + "/.git")
-        .arg("--work-tree=".to_owned() + repo_path)
-        .arg("log")
-        .arg("--pretty=oneline")
-        .output()
-        .unwrap()
-        .stdout;
-
-    let log_output = String::from_utf8_lossy(&output);
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    value.parse::<bool>().map_err(|_| format!("{} has {}, use true or false", key, value))
+}
+
+//Invalid option values (from the flags or the config) end the program like clap ends it for invalid flags, with the usage and no backtrace
+fn or_usage_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|err| usage_error(err))
+}
+
+fn usage_error(err: String) -> ! {
+    Cli::command().error(clap::error::ErrorKind::ValueValidation, err).exit()
+}
+
+//Lists all commits as (sha, message) newest first, the index in this list is what we use as the commit age
+fn get_commit_list(repo_path: &str, options: &RepoOptions) -> Vec<(String, String)> {
+    let repo = Repository::open_ext(repo_path, RepositoryOpenFlags::empty(), Vec::<OsString>::new())
+        .expect("Failed to open repository");
+    let start = repo
+        .revparse_single(&options.start_ref)
+        .expect("Failed to find start ref")
+        .peel_to_commit()
+        .expect("Start ref is not a commit");
+
+    let mut revwalk = repo.revwalk().expect("Failed to create revwalk");
+    revwalk.set_sorting(options.order).expect("Failed to set revwalk order");
+    if options.first_parent {
+        revwalk.simplify_first_parent().expect("Failed to set first parent mode");
+    }
+    revwalk.push(start.id()).expect("Failed to push start commit");
+
     let mut commits = vec![];
-    for line in log_output.lines() {
-        let mut parts = line.splitn(2, ' ');
-        let sha = parts.next().unwrap();
-        let message = parts.next().unwrap();
-        commits.push((sha.to_owned(), message.to_owned()));
+    for oid in revwalk {
+        let oid = oid.expect("Failed to walk commits");
+        let commit = repo.find_commit(oid).expect("Failed to find commit");
+        //the whole message, issue references are often in the body or footer
+        let message = commit.message().unwrap_or("").trim_end().to_owned();
+        commits.push((oid.to_string(), message));
     }
+    commits
+}
 
-    let sha_list = commits;
+//This generates the raw data for analysis from a local repo
+fn generate_json(
+    repo_path: &str,
+    options: &RepoOptions,
+    function_extractors: &[FunctionExtractor],
+) -> RawData {
+    let commits: Vec<(i32, String, String)> = get_commit_list(repo_path, options)
+        .into_iter()
+        .enumerate()
+        .map(|(age, (sha, message))| (age as i32, sha, message))
+        .collect();
+
+    parse_commits(repo_path, &commits, options, function_extractors)
+}
+
+//Same as generate_json but reuses already parsed commits from an earlier run and only diffs the new ones.
+//Ages of the old commits are recalculated from the current log so the result is identical to a full run.
+fn generate_json_incremental(
+    repo_path: &str,
+    mut existing_data: RawData,
+    options: &RepoOptions,
+    function_extractors: &[FunctionExtractor],
+) -> RawData {
+    let mut result = RawData::new();
+    let mut new_commits = vec![];
+
+    for (age, (sha, message)) in get_commit_list(repo_path, options).into_iter().enumerate() {
+        match existing_data.commits.remove(&sha) {
+            Some(mut commit) => {
+                commit.age = age as i32;
+                result.commits.insert(sha, commit);
+            }
+            None => new_commits.push((age as i32, sha, message)),
+        }
+    }
+    //whatever is left in existing_data is no longer reachable (rewritten history) and is dropped
+    println!(
+        "{} new commits to parse, {} reused, {} dropped",
+        new_commits.len(),
+        result.commits.len(),
+        existing_data.commits.len()
+    );
+
+    let new_data = parse_commits(repo_path, &new_commits, options, function_extractors);
+    result.commits.extend(new_data.commits);
+    result
+}
+
+//Diffs every (age, sha, message) commit in parallel and parses the diffs into files and functions
+fn parse_commits(
+    repo_path: &str,
+    sha_list: &[(i32, String, String)],
+    options: &RepoOptions,
+    function_extractors: &[FunctionExtractor],
+) -> RawData {
+    let shared_data = Arc::new(Mutex::new(RawData::new()));
 
     let pb = ProgressBar::new(sha_list.len().try_into().unwrap());
     pb.set_style(
@@ -57,7 +233,7 @@ fn generate_json(repo_path: &str) -> HashMap<String, Vec<(String, Vec<String>, i
             .unwrap(),
     );
 
-    sha_list.par_iter().enumerate().for_each(|(age, sha)| {
+    sha_list.par_iter().for_each(|(age, sha, message)| {
         pb.inc(1);
 
         // Open the repository
@@ -74,105 +250,282 @@ fn generate_json(repo_path: &str) -> HashMap<String, Vec<(String, Vec<String>, i
         };
 
         // Get the commit
-        let commit = match repo.find_commit(Oid::from_str(&sha.0).expect("Invalid OID")) {
+        let commit = match repo.find_commit(Oid::from_str(sha).expect("Invalid OID")) {
             Ok(commit) => commit,
             Err(err) => {
-                println!("Failed to find commit {}: {}", sha.0, err);
+                println!("Failed to find commit {}: {}", sha, err);
                 return;
             }
         };
 
-        // Get the diff
-        let tree1 = commit.tree().expect("Failed to get tree");
-        let tree2 = if commit.parent_count() > 0 {
-            let parent = commit.parent(0).expect("Failed to get parent commit");
-            parent.tree().expect("Failed to get parent tree")
-        } else {
-            repo.revparse_single("HEAD")
-                .expect("Failed to get HEAD")
-                .peel_to_tree()
-                .expect("Failed to peel to tree")
+        // Get the diff, root commits are diffed against an empty tree
+        let tree = commit.tree().expect("Failed to get tree");
+        let parent_trees: Vec<Tree> = commit
+            .parents()
+            .map(|parent| parent.tree().expect("Failed to get parent tree"))
+            .collect();
+        let files = match parent_trees.len() {
+            0 => parse_diff(&repo, None, &tree, function_extractors),
+            1 => parse_diff(&repo, Some(&parent_trees[0]), &tree, function_extractors),
+            _ => match options.merge_policy {
+                MergePolicy::Skip => vec![],
+                MergePolicy::FirstParent => parse_diff(&repo, Some(&parent_trees[0]), &tree, function_extractors),
+                MergePolicy::AllParents => {
+                    let mut files = parse_diff(&repo, Some(&parent_trees[0]), &tree, function_extractors);
+                    for parent_tree in &parent_trees[1..] {
+                        intersect_file_changes(&mut files, parse_diff(&repo, Some(parent_tree), &tree, function_extractors));
+                    }
+                    files
+                }
+            },
         };
-        let diff = repo
-            .diff_tree_to_tree(Some(&tree2), Some(&tree1), None)
-            .expect("Failed to diff trees");
-        let mut diff_text = Vec::new();
-        let _ = diff.print(git2::DiffFormat::Patch, |_, _, line| {
-            diff_text.extend_from_slice(line.content());
-            diff_text.push(b'\n');
-            true
-        });
-
-        let diff_str = String::from_utf8_lossy(&diff_text).to_string();
 
-        let parsed_diff = get_functions_from_diff(&diff_str, age.try_into().unwrap(), &sha.1);
+        let author = if options.mailmap {
+            let mailmap = repo.mailmap().expect("Failed to read mailmap");
+            commit.author_with_mailmap(&mailmap).expect("Failed to resolve author with mailmap")
+        } else {
+            commit.author()
+        };
+        let record = CommitRecord {
+            message: message.to_owned(),
+            age: *age,
+            author_name: author.name().map(|name| name.to_owned()),
+            author_email: author.email().map(|email| email.to_owned()),
+            author_time: Some(author.when().seconds()),
+            committer_time: Some(commit.time().seconds()),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            files,
+            bug_introducing_commits: vec![],
+        };
 
         let mut data = shared_data.lock().unwrap();
-        data.insert(sha.0.to_owned(), parsed_diff);
+        data.commits.insert(sha.to_owned(), record);
         drop(data)
     });
     //this makes us wait for all to finish
     sha_list.par_iter().for_each(|_| {});
 
-    let data = shared_data.lock().unwrap();
-    let returndata = data.clone();
-    returndata
+    let data = std::mem::replace(&mut *shared_data.lock().unwrap(), RawData::new());
+    data
+}
+
+//Diffs old_tree (None = empty tree) to new_tree and parses it into the changed files and their functions
+fn parse_diff(
+    repo: &Repository,
+    old_tree: Option<&Tree>,
+    new_tree: &Tree,
+    function_extractors: &[FunctionExtractor],
+) -> Vec<FileChange> {
+    let mut diff = repo
+        .diff_tree_to_tree(old_tree, Some(new_tree), None)
+        .expect("Failed to diff trees");
+    //detect renames and copies so moved files show up as "rename from/to" instead of a delete and an add
+    diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))
+        .expect("Failed to find renames");
+    //lines keep their +/-/space origin so the parser knows what changed
+    let mut diff_text = Vec::new();
+    let _ = diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        match line.origin() {
+            '+' | '-' | ' ' => diff_text.push(line.origin() as u8),
+            //"no newline at end of file" markers
+            '=' | '>' | '<' => return true,
+            _ => {}
+        }
+        diff_text.extend_from_slice(line.content());
+        if !line.content().ends_with(b"\n") {
+            diff_text.push(b'\n');
+        }
+        true
+    });
+
+    let diff_str = String::from_utf8_lossy(&diff_text).to_string();
+
+    //post-image of every changed file we can extract functions from, used to find enclosing functions
+    let mut post_images: HashMap<String, String> = HashMap::new();
+    for delta in diff.deltas() {
+        let new_file = delta.new_file();
+        let path = match new_file.path() {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => continue,
+        };
+        if new_file.id().is_zero() || get_extractor_for_file(function_extractors, &path).is_none() {
+            continue;
+        }
+        if let Ok(blob) = repo.find_blob(new_file.id()) {
+            if !blob.is_binary() {
+                post_images.insert(path, String::from_utf8_lossy(blob.content()).to_string());
+            }
+        }
+    }
+
+    let mut files = get_functions_from_diff(&diff_str, function_extractors, &post_images);
+
+    //size of the files after the commit, for relative churn
+    for delta in diff.deltas() {
+        let new_file = delta.new_file();
+        let path = match new_file.path() {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => continue,
+        };
+        if new_file.id().is_zero() {
+            continue;
+        }
+        let loc = match post_images.get(&path) {
+            Some(content) => content.lines().count(),
+            None => match repo.find_blob(new_file.id()) {
+                Ok(blob) if !blob.is_binary() => String::from_utf8_lossy(blob.content()).lines().count(),
+                _ => 0,
+            },
+        };
+        if let Some(file) = files.iter_mut().find(|file| file.path == path) {
+            file.loc = loc as u32;
+        }
+    }
+    files
 }
 
-//age and message is passthrough
-//TODO: add file for function regex-writing
-//This function parses entire diff-string and returnsa list of filenames and the functions fixes in those files.
+//Keeps the files that are also in other, a file taken unchanged from one of the parents is not changed by the merge.
+//Functions and line counts are cut down to the ones changed against both, the lines a merge changed show up in the diff against every parent.
+fn intersect_file_changes(files: &mut Vec<FileChange>, other: Vec<FileChange>) {
+    files.retain(|file| other.iter().any(|changed| changed.path == file.path));
+    for file in other {
+        if let Some(existing) = files.iter_mut().find(|existing| existing.path == file.path) {
+            existing.functions.retain(|function| file.functions.contains(function));
+            if existing.old_path.is_none() {
+                existing.old_path = file.old_path;
+            }
+            existing.lines_added = existing.lines_added.min(file.lines_added);
+            existing.lines_deleted = existing.lines_deleted.min(file.lines_deleted);
+            existing.hunks = existing.hunks.min(file.hunks);
+            existing.function_churn.retain(|function, _| file.function_churn.contains_key(function));
+            for (function, churn) in existing.function_churn.iter_mut() {
+                let other_churn = file.function_churn[function];
+                churn.0 = churn.0.min(other_churn.0);
+                churn.1 = churn.1.min(other_churn.1);
+            }
+        }
+    }
+}
+
+//This function parses entire diff-string and returns a list of the changed files and the functions changed in those files.
+//Every changed line is attributed to its enclosing function, found from the function spans of the files post-image.
+//When there is no post-image (deleted file) the last signature seen in the hunk, or the hunk header context, is used instead.
+//Renamed and copied files are listed under their new name, renamed ones also get their old name as old_path.
+//Added/deleted lines and hunks are counted for every file, also the ones without a function extractor.
 fn get_functions_from_diff(
     diff: &str,
-    age: i32,
-    message: &String,
-) -> Vec<(String, Vec<String>, i32, String)> {
-    let regex = Regex::new(r"function\s+[a-zA-Z0-9_]+\(+[a-zA-Z0-9_:, ]*\)|[a-zA-Z0-9]+\s*=\s*\([a-zA-Z0-9: ]*\)\s*=>|[a-zA-Z0-9]+\s*=\s*async\s*\([a-zA-Z0-9: ]*\)\s*=>").unwrap();
-    let name_regex = Regex::new(r"diff --git a/(.*) b").unwrap();
-    let mut files_objects: Vec<(String, Vec<String>, i32, String)> = vec![];
-    let mut curr_filename = String::new();
-    let mut curr_file_functions = vec![];
+    function_extractors: &[FunctionExtractor],
+    post_images: &HashMap<String, String>,
+) -> Vec<FileChange> {
+    let name_regex = Regex::new(r"^diff --git a/(.*) b").unwrap();
+    let post_image_name_regex = Regex::new(r"^\+\+\+ b/(.*)$").unwrap();
+    let rename_from_regex = Regex::new(r"^rename from (.*)$").unwrap();
+    let new_name_regex = Regex::new(r"^(?:rename|copy) to (.*)$").unwrap();
+    let mut rename_from = String::new();
+    let hunk_regex = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)(?:,\d+)? @@(.*)$").unwrap();
+    let mut files: Vec<FileChange> = vec![];
+    let mut curr_file: Option<FileChange> = None;
+    let mut curr_extractor: Option<&FunctionExtractor> = None;
+    let mut curr_spans: Option<Vec<FunctionSpan>> = None;
+    let mut in_hunk = false;
+    let mut new_line_number = 0;
+    let mut last_seen_function: Option<String> = None;
 
     for line in diff.lines() {
         if let Some(name_match) = name_regex.captures(line) {
-            if !curr_filename.is_empty() {
-                files_objects.push((
-                    curr_filename.clone(),
-                    curr_file_functions.clone(),
-                    age,
-                    message.to_string(),
-                ));
+            files.extend(curr_file.take());
+            curr_file = Some(FileChange {
+                path: name_match[1].to_string(),
+                ..Default::default()
+            });
+            curr_extractor = get_extractor_for_file(function_extractors, &name_match[1]);
+            curr_spans = None;
+            in_hunk = false;
+            continue;
+        }
+        let file = match curr_file.as_mut() {
+            Some(file) => file,
+            None => continue,
+        };
+        if !in_hunk {
+            if let Some(rename_match) = rename_from_regex.captures(line) {
+                rename_from = rename_match[1].to_string();
+            } else if let Some(new_name_match) = new_name_regex.captures(line) {
+                file.path = new_name_match[1].to_string();
+                if line.starts_with("rename") {
+                    file.old_path = Some(rename_from.clone());
+                }
+                curr_extractor = get_extractor_for_file(function_extractors, &file.path);
+                continue;
+            }
+        }
+        if let Some(hunk_match) = hunk_regex.captures(line) {
+            in_hunk = true;
+            file.hunks += 1;
+            new_line_number = hunk_match[1].parse::<usize>().unwrap();
+            last_seen_function = curr_extractor.and_then(|extractor| extractor.get_function_name(&hunk_match[2]));
+            continue;
+        }
+        if !in_hunk {
+            if let Some(post_image_match) = post_image_name_regex.captures(line) {
+                file.path = post_image_match[1].to_string();
+                if let (Some(extractor), Some(content)) = (curr_extractor, post_images.get(&file.path)) {
+                    curr_spans = Some(extractor.get_function_spans(content));
+                }
+            }
+            continue;
+        }
+
+        let (origin, code) = match line.chars().next() {
+            Some(origin) => (origin, &line[origin.len_utf8()..]),
+            None => continue,
+        };
+        match origin {
+            '+' => file.lines_added += 1,
+            '-' => file.lines_deleted += 1,
+            _ => {}
+        }
+        let extractor = match curr_extractor {
+            Some(extractor) => extractor,
+            None => {
+                if origin != '-' {
+                    new_line_number += 1;
+                }
+                continue;
+            }
+        };
+        let signature = extractor.get_function_name(code);
+        if signature.is_some() {
+            last_seen_function = signature.clone();
+        }
+        //removed lines sit where new_line_number is now in the post-image
+        let enclosing_function = match &curr_spans {
+            Some(spans) => get_enclosing_function(spans, new_line_number).map(|name| name.to_string()),
+            None => last_seen_function.clone(),
+        };
+        let changed_function = match origin {
+            '+' => enclosing_function,
+            //a removed signature is the removed/renamed function itself
+            '-' => signature.or(enclosing_function),
+            _ => None,
+        };
+        if origin != '-' {
+            new_line_number += 1;
+        }
+        if let Some(function_name) = changed_function {
+            let churn = file.function_churn.entry(function_name.clone()).or_insert((0, 0));
+            if origin == '+' {
+                churn.0 += 1;
+            } else {
+                churn.1 += 1;
+            }
+            if !file.functions.contains(&function_name) {
+                file.functions.push(function_name);
             }
-            curr_filename = name_match[1].to_string();
-            curr_file_functions = vec![];
-        } else if let Some(func_match) = regex.find(line) {
-            let function_name = func_match
-                .as_str()
-                .split('(')
-                .next()
-                .unwrap()
-                .trim()
-                .to_string()
-                .replace("function", "")
-                .replace(" ", "")
-                .replace("=>", "")
-                .replace(" async", "")
-                .replace("= ", "=")
-                .replace(": ", ":")
-                .replace(") ", ")")
-                .replace('=', "");
-            curr_file_functions.push(function_name);
         }
     }
-    if !curr_filename.is_empty() {
-        files_objects.push((
-            curr_filename.clone(),
-            curr_file_functions.clone(),
-            age,
-            message.to_string(),
-        ));
-    }
-    files_objects
+    files.extend(curr_file);
+    files
 }
 
 //Class part, mbe move this
@@ -183,6 +536,9 @@ struct Function {
     bug_counter: f32,
     aged_freq_counter: f32,
     aged_bug_freq_counter: f32,
+    churn_counter: f32,
+    //changes that introduced bugs fixed before the cuttof (see szz)
+    bugs_introduced_counter: f32,
     oldest_newest: (i32, i32),
     times_func_got_bugfixed_after_end_of_measuring: i32,
 }
@@ -201,6 +557,8 @@ impl Function {
             bug_counter,
             aged_freq_counter,
             aged_bug_freq_counter,
+            churn_counter: 0.0,
+            bugs_introduced_counter: 0.0,
             oldest_newest,
             times_func_got_bugfixed_after_end_of_measuring: 0,
         }
@@ -209,19 +567,21 @@ impl Function {
 impl Function {
     fn get_field(&self, n: i32) -> f32 {
         match n {
-            0 => return self.freq_counter,
-            1 => return self.bug_counter,
-            2 => return self.aged_freq_counter,
-            3 => return self.aged_bug_freq_counter,
-            _ => return -1.0,
+            0 => self.freq_counter,
+            1 => self.bug_counter,
+            2 => self.aged_freq_counter,
+            3 => self.aged_bug_freq_counter,
+            4 => self.churn_counter,
+            5 => self.bugs_introduced_counter,
+            _ => -1.0,
         }
     }
 }
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "    {}: bugfixes_after={}, freq={}, bug={}, aged_freq={}, aged_bug_freq={}, oldest_newest={:?}\n",
+            "    {}: bugfixes_after={}, freq={}, bug={}, aged_freq={}, aged_bug_freq={}, oldest_newest={:?}",
             self.name,
             self.times_func_got_bugfixed_after_end_of_measuring,
             self.freq_counter,
@@ -239,6 +599,20 @@ struct File {
     bug_counter: f32,
     aged_freq_counter: f32,
     aged_bug_freq_counter: f32,
+    decayed_freq_counter: f32,
+    decayed_bug_counter: f32,
+    //lines added + deleted
+    churn_counter: f32,
+    bug_churn_counter: f32,
+    //lines in the file at its newest change
+    loc: u32,
+    //author -> commits to the file
+    authors: HashMap<String, f32>,
+    //files changed together with this one (see co_change) and the fixed bugs in them
+    coupled_files: f32,
+    coupled_bug_counter: f32,
+    //changes that introduced bugs fixed before the cuttof (see szz)
+    bugs_introduced_counter: f32,
     oldest_newest: (i32, i32),
     function_list: HashMap<String, Function>,
     times_file_got_bugfixed_after_end_of_measuring: i32,
@@ -246,83 +620,36 @@ struct File {
     times_functions_got_bugfiexed_after_file_data: i32,
     repo_max_age: i32,
 }
-//The matches in This function needs to match amount in "get_implemented_nr_of_fields_for_analysis" and corresponds to "get_field"
-//The naming is bad
-fn get_file_field_name(n: i32) -> String {
-    let _ret = "ERROR no field for: ".to_owned() + &n.to_string();
-    match n {
-        0 => return "frequency".to_string(),
-        1 => return "fixed bugs".to_string(),
-        2 => return "oldest change".to_string(),
-        3 => return "newest change".to_string(),
-        4 => return "frequency aged by commit ages".to_string(),
-        5 => return "fixed bugs aged by commit ages".to_string(),
-        6 => return "frequency aged by most recent newest file change".to_string(),
-        7 => return "fixed bugs aged by most recent newest file change".to_string(),
-        8 => return "frequency aged by most recent oldest file change ".to_string(),
-        9 => return "fixed bugs aged by most recent oldest file change ".to_string(),
-
-        10 => return "frequency aged by commit ages * newest change".to_string(),
-        11 => return "fixed bugs aged by commit ages * newest change".to_string(),
-
-        12 => return "custom formula".to_string(),
-        13 => return "custom formula freq1".to_string(),
-        14 => return "custom formula freq2".to_string(),
-        15 => return "custom formula bug1".to_string(),
-        16 => return "custom formula bug2".to_string(),
-
-        17 => return "custom formula freqonly".to_string(),
-        18 => return "custom formula bugonly".to_string(),
-
-        19 => return "custom formula more newest change".to_string(),
-        20 => return "custom formula freq1  more newest change".to_string(),
-        21 => return "custom formula freq2  more newest change".to_string(),
-        22 => return "custom formula bug1  more newest change".to_string(),
-        23 => return "custom formula bug2  more newest change".to_string(),
-
-        24 => return "custom formula freqonly  more newest change".to_string(),
-        25 => return "custom formula bugonly  more newest change".to_string(),
-        _ => return "!!!!!!!!ERROR unknown field!!!!!!!!!!!".to_string(),
-    }
-}
 impl File {
-    //The matches in This function needs to match amount in "get_implemented_nr_of_fields_for_analysis" and corresponds to "get_file_field_name"
-    fn get_field(&self, n: i32) -> f32 {
-        match n {
-            0 => self.freq_counter,
-            1 => self.bug_counter,
-            2 => self.oldest_newest.0 as f32,
-            3 => self.oldest_newest.1 as f32,
-            4 => self.aged_freq_counter,
-            5 => self.aged_bug_freq_counter,
-            6 => self.freq_counter * self.oldest_newest.1 as f32,
-            7 => self.bug_counter * self.oldest_newest.1 as f32,
-            8 => self.freq_counter * self.oldest_newest.0 as f32,
-            9 => self.bug_counter * self.oldest_newest.0 as f32,
-
-            10 => self.oldest_newest.1 as f32 * self.aged_freq_counter,
-            11 => self.oldest_newest.1 as f32 * self.aged_bug_freq_counter,
-
-            12 => self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter,
-            13 => self.oldest_newest.1 as f32 * self.aged_freq_counter * 2.0 + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter,
-            14 => self.oldest_newest.1 as f32 * self.aged_freq_counter * 10.0 + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter ,
-            15 => self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter * 2.0,
-            16 => self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter * 10.0,
-            //singular
-            17 => self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * 1.0,
-            18 => self.oldest_newest.1 as f32 * self.aged_bug_freq_counter + self.oldest_newest.1 as f32 * 1.0,
-            //more prio on newest change
-            19 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter,
-            20 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter * 2.0 + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter,
-            21 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter * 10.0 + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter ,
-            22 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter * 2.0,
-            23 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter * 10.0,
-
-            24 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_freq_counter + self.oldest_newest.1 as f32 * 1.0,
-            25 => self.oldest_newest.1 as f32 + self.oldest_newest.1 as f32 * self.aged_bug_freq_counter + self.oldest_newest.1 as f32 * 1.0,
-            _ => -1.0,
+    //The value of a variable in factor formulas for this file
+    fn get_variable(&self, variable: Variable) -> f32 {
+        match variable {
+            Variable::Freq => self.freq_counter,
+            Variable::Bugs => self.bug_counter,
+            Variable::AgedFreq => self.aged_freq_counter,
+            Variable::AgedBugs => self.aged_bug_freq_counter,
+            Variable::Oldest => self.oldest_newest.0 as f32,
+            Variable::Newest => self.oldest_newest.1 as f32,
+            //time weighted, every commit counts half as much per half-life since the newest commit
+            Variable::DecayedFreq => self.decayed_freq_counter,
+            Variable::DecayedBugs => self.decayed_bug_counter,
+            //changed lines
+            Variable::Churn => self.churn_counter,
+            Variable::BugChurn => self.bug_churn_counter,
+            Variable::Loc => self.loc as f32,
+            //ownership, a minor contributor made less than 5% of the commits to the file
+            Variable::Authors => self.authors.len() as f32,
+            Variable::MinorAuthors => self.authors.values().filter(|commits| **commits / self.freq_counter < 0.05).count() as f32,
+            Variable::TopOwner => self.authors.values().cloned().fold(0.0, f32::max) / self.freq_counter.max(1.0),
+            Variable::CoupledFiles => self.coupled_files,
+            Variable::CoupledBugs => self.coupled_bug_counter,
+            Variable::BugsIntroduced => self.bugs_introduced_counter,
+            Variable::MaxAge => self.repo_max_age as f32,
         }
     }
+    fn get_factor_value(&self, factor: &Factor) -> f32 {
+        factor.evaluate(&|variable| self.get_variable(variable))
+    }
     //unused
     fn _insert_function_bugfix(&mut self, function_name: String) {
         if self
@@ -343,9 +670,9 @@ impl File {
     }
     //unused
     fn _get_sorted_function_vec_by_field(&self, field: i32) -> Vec<&Function> {
-        let mut fn_list: Vec<&Function> = self.function_list.values().into_iter().collect();
+        let mut fn_list: Vec<&Function> = self.function_list.values().collect();
         fn_list.sort_by(|a, b| b.get_field(field).total_cmp(&a.get_field(field)));
-        return fn_list;
+        fn_list
     }
     //unused
     fn _new(
@@ -363,6 +690,15 @@ impl File {
             bug_counter,
             aged_freq_counter,
             aged_bug_freq_counter,
+            decayed_freq_counter: 0.0,
+            decayed_bug_counter: 0.0,
+            churn_counter: 0.0,
+            bug_churn_counter: 0.0,
+            loc: 0,
+            authors: HashMap::new(),
+            coupled_files: 0.0,
+            coupled_bug_counter: 0.0,
+            bugs_introduced_counter: 0.0,
             oldest_newest,
             function_list: HashMap::new(),
             times_file_got_bugfixed_after_end_of_measuring: 0,
@@ -378,9 +714,9 @@ impl File {
 }
 impl fmt::Display for File {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "File {}: file_bugfixes_after={},freq={}, bug={}, aged_freq={}, aged_bug_freq={}, oldest_newest={:?}, total_fixes_in_file={}\n",
+            "File {}: file_bugfixes_after={},freq={}, bug={}, aged_freq={}, aged_bug_freq={}, oldest_newest={:?}, total_fixes_in_file={}",
             self.name,
             self.times_file_got_bugfixed_after_end_of_measuring,
             self.freq_counter,
@@ -398,6 +734,31 @@ impl fmt::Display for File {
     }
 }
 
+//What one commit adds to the counters of a file, add_file sums these per file
+struct FileCounters {
+    freq_counter: f32,
+    bug_counter: f32,
+    aged_freq_counter: f32,
+    aged_bug_freq_counter: f32,
+    decayed_freq_counter: f32,
+    decayed_bug_counter: f32,
+    churn_counter: f32,
+    bug_churn_counter: f32,
+    //lines of the file after the commit, the newest commit (smallest age) gives the loc of the file
+    loc: u32,
+    oldest_newest: (i32, i32),
+}
+
+//What one commit adds to the counters of a function, add_function sums these per function
+struct FunctionCounters {
+    freq_counter: f32,
+    bug_counter: f32,
+    aged_freq_counter: f32,
+    aged_bug_freq_counter: f32,
+    churn_counter: f32,
+    oldest_newest: (i32, i32),
+}
+
 #[derive(Serialize, Deserialize)]
 struct FileList {
     files: HashMap<String, File>,
@@ -432,7 +793,7 @@ impl FileList {
     fn new(max_age: usize) -> FileList {
         FileList {
             files: (HashMap::new()),
-            max_age: max_age,
+            max_age,
             files_bugfixed_after_file_list: (HashMap::new()),
             total_bugfixes_after_file_list: 0,
         }
@@ -440,34 +801,52 @@ impl FileList {
     fn add_file(
         &mut self,
         filename: &str,
-        freq_counter: f32,
-        bug_counter: f32,
-        aged_freq_counter: f32,
-        aged_bug_freq_counter: f32,
-        oldest_newest: (i32, i32),
+        counters: FileCounters,
+        author: Option<&str>,
         repo_max_age: i32,
     ) {
         if let Some(file) = self.files.get_mut(filename) {
             // Update existing file
-            file.freq_counter += freq_counter;
-            file.bug_counter += bug_counter;
-            file.aged_freq_counter += aged_freq_counter;
-            file.aged_bug_freq_counter += aged_bug_freq_counter;
-            if oldest_newest.0 < file.oldest_newest.0 {
-                file.oldest_newest.0 = oldest_newest.0;
+            file.freq_counter += counters.freq_counter;
+            file.bug_counter += counters.bug_counter;
+            file.aged_freq_counter += counters.aged_freq_counter;
+            file.aged_bug_freq_counter += counters.aged_bug_freq_counter;
+            file.decayed_freq_counter += counters.decayed_freq_counter;
+            file.decayed_bug_counter += counters.decayed_bug_counter;
+            file.churn_counter += counters.churn_counter;
+            file.bug_churn_counter += counters.bug_churn_counter;
+            if let Some(author) = author {
+                *file.authors.entry(author.to_string()).or_insert(0.0) += counters.freq_counter;
+            }
+            if counters.oldest_newest.0 < file.oldest_newest.0 {
+                file.oldest_newest.0 = counters.oldest_newest.0;
+                file.loc = counters.loc;
             }
-            if oldest_newest.1 > file.oldest_newest.1 {
-                file.oldest_newest.1 = oldest_newest.1;
+            if counters.oldest_newest.1 > file.oldest_newest.1 {
+                file.oldest_newest.1 = counters.oldest_newest.1;
             }
         } else {
             // Add new file
+            let mut authors = HashMap::new();
+            if let Some(author) = author {
+                authors.insert(author.to_string(), counters.freq_counter);
+            }
             let file = File {
                 name: filename.to_string(),
-                freq_counter,
-                bug_counter,
-                aged_freq_counter,
-                aged_bug_freq_counter,
-                oldest_newest,
+                freq_counter: counters.freq_counter,
+                bug_counter: counters.bug_counter,
+                aged_freq_counter: counters.aged_freq_counter,
+                aged_bug_freq_counter: counters.aged_bug_freq_counter,
+                decayed_freq_counter: counters.decayed_freq_counter,
+                decayed_bug_counter: counters.decayed_bug_counter,
+                churn_counter: counters.churn_counter,
+                bug_churn_counter: counters.bug_churn_counter,
+                loc: counters.loc,
+                authors,
+                coupled_files: 0.0,
+                coupled_bug_counter: 0.0,
+                bugs_introduced_counter: 0.0,
+                oldest_newest: counters.oldest_newest,
                 function_list: HashMap::new(),
                 times_file_got_bugfixed_after_end_of_measuring: 0,
                 functions_bugfixed_after_file_data: HashMap::new(),
@@ -482,35 +861,34 @@ impl FileList {
         &mut self,
         filename: &str,
         function_name: &str,
-        freq_counter: f32,
-        bug_counter: f32,
-        aged_freq_counter: f32,
-        aged_bug_freq_counter: f32,
-        oldest_newest: (i32, i32),
+        counters: FunctionCounters,
         repo_max_age: i32,
     ) {
         if let Some(file) = self.files.get_mut(filename) {
             if let Some(function) = file.function_list.get_mut(function_name) {
                 // Update existing function
-                function.freq_counter += freq_counter;
-                function.bug_counter += bug_counter;
-                function.aged_freq_counter += aged_freq_counter;
-                function.aged_bug_freq_counter += aged_bug_freq_counter;
-                if oldest_newest.0 < function.oldest_newest.0 {
-                    function.oldest_newest.0 = oldest_newest.0;
+                function.freq_counter += counters.freq_counter;
+                function.bug_counter += counters.bug_counter;
+                function.aged_freq_counter += counters.aged_freq_counter;
+                function.aged_bug_freq_counter += counters.aged_bug_freq_counter;
+                function.churn_counter += counters.churn_counter;
+                if counters.oldest_newest.0 < function.oldest_newest.0 {
+                    function.oldest_newest.0 = counters.oldest_newest.0;
                 }
-                if oldest_newest.1 > function.oldest_newest.1 {
-                    function.oldest_newest.1 = oldest_newest.1;
+                if counters.oldest_newest.1 > function.oldest_newest.1 {
+                    function.oldest_newest.1 = counters.oldest_newest.1;
                 }
             } else {
                 // Add new function
                 let function = Function {
                     name: function_name.to_string(),
-                    freq_counter,
-                    bug_counter,
-                    aged_freq_counter,
-                    aged_bug_freq_counter,
-                    oldest_newest,
+                    freq_counter: counters.freq_counter,
+                    bug_counter: counters.bug_counter,
+                    aged_freq_counter: counters.aged_freq_counter,
+                    aged_bug_freq_counter: counters.aged_bug_freq_counter,
+                    churn_counter: counters.churn_counter,
+                    bugs_introduced_counter: 0.0,
+                    oldest_newest: counters.oldest_newest,
                     times_func_got_bugfixed_after_end_of_measuring: 0,
                 };
                 file.function_list
@@ -520,11 +898,20 @@ impl FileList {
             // Add new file with new function
             let mut file = File {
                 name: filename.to_string(),
-                freq_counter,
-                bug_counter,
-                aged_freq_counter,
-                aged_bug_freq_counter,
-                oldest_newest,
+                freq_counter: counters.freq_counter,
+                bug_counter: counters.bug_counter,
+                aged_freq_counter: counters.aged_freq_counter,
+                aged_bug_freq_counter: counters.aged_bug_freq_counter,
+                decayed_freq_counter: 0.0,
+                decayed_bug_counter: 0.0,
+                churn_counter: 0.0,
+                bug_churn_counter: 0.0,
+                loc: 0,
+                authors: HashMap::new(),
+                coupled_files: 0.0,
+                coupled_bug_counter: 0.0,
+                bugs_introduced_counter: 0.0,
+                oldest_newest: counters.oldest_newest,
                 function_list: HashMap::new(),
                 times_file_got_bugfixed_after_end_of_measuring: 0,
                 functions_bugfixed_after_file_data: HashMap::new(),
@@ -533,11 +920,13 @@ impl FileList {
             };
             let function = Function {
                 name: function_name.to_string(),
-                freq_counter,
-                bug_counter,
-                aged_freq_counter,
-                aged_bug_freq_counter,
-                oldest_newest,
+                freq_counter: counters.freq_counter,
+                bug_counter: counters.bug_counter,
+                aged_freq_counter: counters.aged_freq_counter,
+                aged_bug_freq_counter: counters.aged_bug_freq_counter,
+                churn_counter: counters.churn_counter,
+                bugs_introduced_counter: 0.0,
+                oldest_newest: counters.oldest_newest,
                 times_func_got_bugfixed_after_end_of_measuring: 0,
             };
             file.function_list
@@ -567,12 +956,12 @@ struct Child {
 }
 impl Child {
     fn new(name: String, group: String, value: f32, colname: String) -> Child {
-        return Child {
-            name: (name),
-            group: (group),
-            value: (value),
-            colname: (colname),
-        };
+        Child {
+            name,
+            group,
+            value,
+            colname,
+        }
     }
 }
 
@@ -595,16 +984,13 @@ impl Parent {
     }
     fn sort_children_by_value(&mut self) {
         self.children
-            .sort_by(|b, a| a.value.partial_cmp(&b.value).unwrap());
+            .sort_by(|b, a| a.value.total_cmp(&b.value));
     }
-    fn remove_children_with_ending(&mut self, endings: &Vec<Regex>) {
+    fn remove_children_with_ending(&mut self, endings: &[Regex]) {
         let filter = |name: &str| -> bool {
-
-            if endings
+            endings
                 .iter()
-                .any(|regex| regex.is_match(&name)){return true}
-
-            return false;
+                .any(|regex| regex.is_match(name))
         };
 
         let mut i = 0;
@@ -628,19 +1014,20 @@ impl Container {
         self.children.sort_by(|a, b| {
             let a_total_value: f32 = a.children.iter().map(|child| child.value).sum();
             let b_total_value: f32 = b.children.iter().map(|child| child.value).sum();
-            b_total_value.partial_cmp(&a_total_value).unwrap()
+            b_total_value.total_cmp(&a_total_value)
         });
     }
 
     fn _new(name: String, children: Vec<Parent>) -> Container {
-        return Container {
-            name: (name),
-            children: (children),
-        };
+        Container {
+            name,
+            children,
+        }
     }
 }
 
-fn filelist_to_container(filelist: FileList, field: i32) -> Container {
+//function_field is the field of Function used for the function children
+fn filelist_to_container(filelist: FileList, factor: &Factor, function_field: i32) -> Container {
 
     let child_vec: Vec<Parent> = vec![];
     let pb = ProgressBar::new(filelist.files.len().try_into().unwrap());
@@ -662,7 +1049,7 @@ fn filelist_to_container(filelist: FileList, field: i32) -> Container {
                 let child = Child::new(
                     function_name.to_owned(),
                     file_name.to_owned(),
-                    function.get_field(field),
+                    function.get_field(function_field),
                     "level3".to_owned(),
                 );
                 children.push(child);
@@ -672,7 +1059,7 @@ fn filelist_to_container(filelist: FileList, field: i32) -> Container {
         let parent = Parent {
             name: file_name.to_owned(),
             children,
-            value: file.get_field(field),
+            value: file.get_factor_value(factor),
             colname: "level2".to_owned(),
         };
         let mut data = shared_container.lock().unwrap();
@@ -683,11 +1070,10 @@ fn filelist_to_container(filelist: FileList, field: i32) -> Container {
     filelist.files.par_iter().for_each(|_| {});
 
     let data = shared_container.lock().unwrap().clone();
-    let container = Container {
+    Container {
         name: "Container".to_string(),
         children: data,
-    };
-    return container;
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -716,7 +1102,7 @@ impl Folder {
         let parts = path.split('/');
         let mut current_folder = self;
         for part in parts {
-            if part == "" {
+            if part.is_empty() {
                 continue;
             }
             match current_folder.subfolders.get(part) {
@@ -738,7 +1124,7 @@ impl Folder {
         let mut current_folder = self;
 
         for part in parts.clone().take(parts.clone().count() - 1) {
-            if part == "" {
+            if part.is_empty() {
                 continue;
             }
 
@@ -751,11 +1137,10 @@ impl Folder {
             }
         }
 
-        let file_name = String::from(parts.last().unwrap());
+        let file_name = String::from(path.split('/').next_back().unwrap());
         let file = FolderFile { name: file_name.clone(), value };
-        let existing_file = current_folder.files.get_mut(&file_name);
-        if existing_file.is_some() {
-            existing_file.unwrap().value += value;
+        if let Some(existing_file) = current_folder.files.get_mut(&file_name) {
+            existing_file.value += value;
         } else {
             current_folder.files.insert(file_name, file);
         }
@@ -765,7 +1150,7 @@ impl Folder {
         let mut current_folder = self;
 
         for part in parts {
-            if part == "" {
+            if part.is_empty() {
                 continue;
             }
             match current_folder.subfolders.get(part) {
@@ -784,7 +1169,7 @@ impl Folder {
             folder.get_total_value()
         }).sum();
 
-        result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        result.sort_by(|a, b| b.1.total_cmp(&a.1));
 
         Some(result)
     }
@@ -798,7 +1183,7 @@ impl Folder {
         };
 
 
-        let items = self.get_path_items(&path).unwrap();
+        let items = self.get_path_items(path).unwrap();
         for item in items {
             let child = Child::new(
                 item.0.to_owned(),
@@ -815,7 +1200,7 @@ impl Folder {
         };
         container.children.push(parent);
 
-        return container;
+        container
     }
     fn print_folder_structure(&self, depth: u32) -> String {
         let mut result = String::new();
@@ -824,7 +1209,7 @@ impl Folder {
         result.push_str(&format!("{}{} - {:.2}\n", indent, self.name, self.get_total_value()));
 
         let mut subfolders: Vec<&Folder> = self.subfolders.values().collect();
-        subfolders.sort_by(|a, b| b.get_total_value().partial_cmp(&a.get_total_value()).unwrap());
+        subfolders.sort_by(|a, b| b.get_total_value().total_cmp(&a.get_total_value()));
 
         for folder in subfolders {
             result.push_str(&folder.print_folder_structure(depth + 1));
@@ -843,17 +1228,17 @@ impl fmt::Display for FolderFile {
 
 impl fmt::Display for Folder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}\n", self.name, self.get_total_value())?;
+        writeln!(f, "{}: {}", self.name, self.get_total_value())?;
         let mut file_list = Vec::from_iter(self.files.iter());
-        file_list.sort_by(|b,a|a.1.value.partial_cmp(&b.1.value).unwrap());
+        file_list.sort_by(|b,a|a.1.value.total_cmp(&b.1.value));
         for (_, file) in file_list {
-            write!(f, "-- {}\n", file)?;
+            writeln!(f, "-- {}", file)?;
         }
-        write!(f, "\n")?;
+        writeln!(f)?;
         let mut folder_list = Vec::from_iter(self.subfolders.iter());
-        folder_list.sort_by(|b,a|a.1.get_total_value().partial_cmp(&b.1.get_total_value()).unwrap());
+        folder_list.sort_by(|b,a|a.1.get_total_value().total_cmp(&b.1.get_total_value()));
         for (_, folder) in folder_list {
-            write!(f, "{}\\{} \n",self.name , folder)?;
+            writeln!(f, "{}\\{} ",self.name , folder)?;
         }
         Ok(())
     }
@@ -861,9 +1246,9 @@ impl fmt::Display for Folder {
 //END of classes
 
 //Converts filelist to container
-fn filelist_to_container_only_files(filelist: &FileList, field: i32) -> Container {
+fn filelist_to_container_only_files(filelist: &FileList, factor: &Factor) -> Container {
     let mut parentlist = vec![];
-    for (_, file) in &filelist.files {
+    for file in filelist.files.values() {
         let shortname = file.name.clone().split("/").last().unwrap().to_string();
         let mut parent = Parent {
             name: shortname.clone(),
@@ -874,7 +1259,7 @@ fn filelist_to_container_only_files(filelist: &FileList, field: i32) -> Containe
         let child = Child {
             name: shortname.clone(),
             group: file.name.clone(),
-            value: file.get_field(field),
+            value: file.get_factor_value(factor),
             colname: "level3".to_owned(),
         };
         parent.value = 0.0;
@@ -887,34 +1272,156 @@ fn filelist_to_container_only_files(filelist: &FileList, field: i32) -> Containe
     }
 }
 
+//What the ages of commits are measured in when analyzing raw data
+#[derive(PartialEq)]
+enum AgeMode {
+    //position in the commit list, 0 is the newest commit
+    Position,
+    //whole days between the commit and the newest commit, by committer or author time
+    CommitterDays,
+    AuthorDays,
+}
+
+//Options for how the analysis modes use ages, key=value options from the flags and the config
+struct AgeOptions {
+    mode: AgeMode,
+    //age for the weight of a commit to halve in the decayed factors, in the unit of the ages. None is 10% of the max age
+    half_life: Option<f32>,
+}
+
+//Parses "ages=position|days|author_days" and "half_life=<number>" args, other args are ignored
+fn parse_age_options(args: &[String]) -> Result<AgeOptions, String> {
+    let mut options = AgeOptions {
+        mode: AgeMode::Position,
+        half_life: None,
+    };
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("ages", "position")) => options.mode = AgeMode::Position,
+            Some(("ages", "days")) => options.mode = AgeMode::CommitterDays,
+            Some(("ages", "author_days")) => options.mode = AgeMode::AuthorDays,
+            Some(("ages", value)) => return Err(format!("unknown ages {}, use position, days or author_days", value)),
+            Some(("half_life", value)) => {
+                options.half_life = Some(value.parse::<f32>().map_err(|_| format!("half_life has {} which is not a number", value))?)
+            }
+            _ => {}
+        }
+    }
+    Ok(options)
+}
+
+//horizon_days counts days, parse_evaluation_grid only takes it with day ages but raw data without commit times falls back to positions
+fn check_horizon(horizon: Option<Horizon>, age_options: &AgeOptions) -> Result<(), String> {
+    match horizon {
+        Some(Horizon::Days(_)) if age_options.mode == AgeMode::Position => {
+            Err("horizon_days needs raw data with commit times, generate it again with \"repo\"".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+//Changes the ages in raw_data from positions to days since the newest commit when the options ask for it.
+//Raw data without commit times (generated before they were saved) keeps position ages.
+fn apply_age_options(raw_data: &mut RawData, age_options: &mut AgeOptions) {
+    if age_options.mode == AgeMode::Position {
+        return;
+    }
+    let commit_time = |commit: &CommitRecord| {
+        if age_options.mode == AgeMode::AuthorDays { commit.author_time } else { commit.committer_time }
+    };
+    if raw_data.commits.values().any(|commit| !commit.files.is_empty() && commit_time(commit).is_none()) {
+        println!("raw data has no commit times, generate it again with \"repo\" to use day ages. Using position ages");
+        age_options.mode = AgeMode::Position;
+        return;
+    }
+    let newest_time = raw_data.commits.values().filter_map(commit_time).max().unwrap_or(0);
+    for commit in raw_data.commits.values_mut() {
+        if let Some(time) = commit_time(commit) {
+            commit.age = ((newest_time - time) / (60 * 60 * 24)) as i32;
+        }
+    }
+}
+
+//What the commits are counted with, the same for every window of a subcommand
+#[derive(Clone, Copy)]
+struct CountingOptions<'a> {
+    bugfix_classifier: &'a dyn BugfixClassifier,
+    ground_truth: GroundTruth,
+    filtered_filetypes: &'a [Regex],
+    rename_map: &'a RenameMap,
+    age_options: &'a AgeOptions,
+}
+
 //This function does all the counting of factors we want to extract from the generated data of commits
 fn file_data_map_to_file_list(
-    file_data: &HashMap<String, Vec<(String, Vec<String>, i32, String)>>,
-    age_limit: usize,
-    recognized_bugfix_indicators: &Vec<Regex>,
-    filtered_filetypes: &Vec<Regex>,
+    raw_data: &RawData,
+    window: EvaluationWindow,
+    counting: &CountingOptions,
 ) -> FileList {
-    let max_age = file_data.len();
-
-    let age_precentage_to_int: i32 = (max_age as f32 * (age_limit as f32 / 100.0)) as i32;
+    let CountingOptions { bugfix_classifier, ground_truth, filtered_filetypes, rename_map, age_options } = *counting;
+    let max_age = match age_options.mode {
+        AgeMode::Position => raw_data.commits.len(),
+        _ => raw_data.commits.values().map(|commit| commit.age.max(0) as usize + 1).max().unwrap_or(1),
+    };
+    let half_life = age_options.half_life.unwrap_or(max_age as f32 / 10.0);
+
+    let age_precentage_to_int: i32 = (max_age as f32 * (window.cuttof as f32 / 100.0)) as i32;
+    //commits newer than the window start or older than its end are not used at all, a plain cuttof uses every commit
+    let train_start_to_int: i32 = if window.train_start == 0 { i32::MIN } else { (max_age as f32 * (window.train_start as f32 / 100.0)) as i32 };
+    //the decayed factors weigh the newest counted commit 1, the window start or the newest commit
+    let decay_start = train_start_to_int.max(0);
+    let mut test_end_to_int: i32 = if window.test_end == 100 { i32::MAX } else { (max_age as f32 * (window.test_end as f32 / 100.0)) as i32 };
+    //the horizon moves the end closer to the cuttof, never past the window end
+    match window.horizon {
+        Some(Horizon::Commits(commits)) => {
+            let mut test_ages: Vec<i32> = raw_data.commits.values()
+                .filter(|commit| !commit.files.is_empty() && commit.age > age_precentage_to_int && commit.age <= test_end_to_int)
+                .map(|commit| commit.age)
+                .collect();
+            test_ages.sort();
+            if let Some(age) = test_ages.get(commits - 1) {
+                test_end_to_int = *age;
+            }
+        },
+        //check_horizon has made sure the ages are days
+        Some(Horizon::Days(days)) => {
+            test_end_to_int = test_end_to_int.min(age_precentage_to_int.saturating_add(days as i32));
+        },
+        None => {},
+    }
 
     let mut file_list: FileList = FileList::new(max_age - 1);
-    //"files" represents a commit
-    for (_, files) in file_data {
-        //If relevant & after age_limit
-        if (files.len() > 0) && (files[0].2) > age_precentage_to_int as i32 {
+    //the commits before the cuttof first, a bug fixed after it is only counted for files that are already in the list
+    let mut commits: Vec<(&String, &CommitRecord)> = raw_data.commits.iter().collect();
+    commits.sort_by_key(|(sha, commit)| (commit.age > age_precentage_to_int, commit.age, *sha));
+    for (sha, commit) in commits {
+        let age = &commit.age;
+        let author = commit.get_author_identity();
+        if commit.age < train_start_to_int || commit.age > test_end_to_int {
+            continue;
+        }
+        let is_bugfix = bugfix_classifier.is_bugfix(sha, commit);
+        //If relevant & after cuttof
+        if !commit.files.is_empty() && commit.age > age_precentage_to_int {
             // post-cuttof functionality counts bugg fixed after cuttoff
-            for (filename, functions, _age, message) in files {
+            for file in &commit.files {
+                //count the change under the name the file has now
+                let filename = &rename_map.get_current_name(&file.path, sha);
                 if filtered_filetypes
                     .iter()
-                    .any(|regex| regex.is_match(&filename)){
+                    .any(|regex| regex.is_match(filename)){
                         continue;
                     }
-                //If we are bugfix
-                if recognized_bugfix_indicators
-                    .iter()
-                    .any(|regex| regex.is_match(&message))
-                {
+                //the bugs this change counts as, 1 if we are bugfix or the later fixes of the bugs it introduced
+                let (bugs, function_bugs): (i32, Vec<(&String, i32)>) = match ground_truth {
+                    GroundTruth::Fixes if is_bugfix => (1, file.functions.iter().map(|function| (function, 1)).collect()),
+                    GroundTruth::Fixes => (0, vec![]),
+                    GroundTruth::Introduced => (
+                        file.fixed_by.len() as i32,
+                        file.function_fixed_by.iter().map(|(function, fixes)| (function, fixes.len() as i32)).collect(),
+                    ),
+                };
+                if bugs > 0 {
 
                     //if we have a fix on file that didnt exist before cuttof, simply ignore it
                     if !file_list.files.contains_key(filename) {
@@ -922,395 +1429,506 @@ fn file_data_map_to_file_list(
                     }
 
                     let changed_file = file_list.files.get_mut(filename).unwrap();
-                    file_list.total_bugfixes_after_file_list += 1;
-                    changed_file.times_file_got_bugfixed_after_end_of_measuring += 1;
+                    file_list.total_bugfixes_after_file_list += bugs;
+                    changed_file.times_file_got_bugfixed_after_end_of_measuring += bugs;
                     //This part does put all needed data for functions into file_list
-                    for function in functions {
+                    for (function, bugs) in function_bugs {
                         //if newer function than cuttof, ignore
                         if !changed_file.function_list.contains_key(function) {
                             continue;
                         }
-                        changed_file.times_functions_got_bugfiexed_after_file_data += 1;
+                        changed_file.times_functions_got_bugfiexed_after_file_data += bugs;
                         changed_file
                             .function_list
                             .get_mut(function)
                             .unwrap()
-                            .times_func_got_bugfixed_after_end_of_measuring += 1;
+                            .times_func_got_bugfixed_after_end_of_measuring += bugs;
 
                     }
                 };
             }
         } else {
             //pre-cuttof functionality adds everything to list from single commit
-            for (filename, functions, age, message) in files {
+            for file in &commit.files {
+                let filename = &rename_map.get_current_name(&file.path, sha);
                 let mut bug_counter = 0.0;
-                if recognized_bugfix_indicators
-                    .iter()
-                    .any(|regex| regex.is_match(&message))
-                {
+                if is_bugfix {
                     bug_counter += 1.0;
                 };
+                let churn = (file.lines_added + file.lines_deleted) as f32;
                 //add_file adds values to existing file if it is in list
                 file_list.add_file(
-                    &filename,
-                    1.0,
-                    bug_counter,
-                    ((age.to_owned() as f32 / (max_age as f32)) * 100.0).round() / 100.0,
-                    ((bug_counter * (age.to_owned() as f32 / (max_age as f32)))*100.0).round() / 100.0,
-                    (age.to_owned(), age.to_owned()),
+                    filename,
+                    FileCounters {
+                        freq_counter: 1.0,
+                        bug_counter,
+                        aged_freq_counter: ((age.to_owned() as f32 / (max_age as f32)) * 100.0).round() / 100.0,
+                        aged_bug_freq_counter: ((bug_counter * (age.to_owned() as f32 / (max_age as f32)))*100.0).round() / 100.0,
+                        decayed_freq_counter: 0.5_f32.powf((age - decay_start) as f32 / half_life),
+                        decayed_bug_counter: bug_counter * 0.5_f32.powf((age - decay_start) as f32 / half_life),
+                        churn_counter: churn,
+                        bug_churn_counter: bug_counter * churn,
+                        loc: file.loc,
+                        oldest_newest: (age.to_owned(), age.to_owned()),
+                    },
+                    author.as_deref(),
                     file_list.max_age as i32,
                 );
-                for func_name in functions {
+                for func_name in &file.functions {
                     //add_function adds values to existing func if it is in list
                     file_list.add_function(
-                        &filename,
-                        &func_name,
-                        1.0,
-                        bug_counter,
-                        0.0,
-                        0.0,
-                        (age.to_owned(), age.to_owned()),
+                        filename,
+                        func_name,
+                        FunctionCounters {
+                            freq_counter: 1.0,
+                            bug_counter,
+                            aged_freq_counter: 0.0,
+                            aged_bug_freq_counter: 0.0,
+                            churn_counter: file.function_churn.get(func_name).map(|(added, deleted)| (added + deleted) as f32).unwrap_or(0.0),
+                            oldest_newest: (age.to_owned(), age.to_owned()),
+                        },
                         file_list.max_age as i32,
                     )
                 }
-            }
-        }
-    }
-    file_list
-}
-
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    //Reads regexes to filter from file
-    let raw_string_filtered_file_types = std::fs::read_to_string("regex_filtered_file_types.json").unwrap();
-    let parsed_raw_string_filtered_file_types: Vec<String> = serde_json::from_str(&raw_string_filtered_file_types).unwrap();
-    let mut filtered_file_types =vec![];
-    if parsed_raw_string_filtered_file_types.len() > 0{
-        for string in parsed_raw_string_filtered_file_types{
-            let reg = Regex::new(&string).unwrap();
-            filtered_file_types.push(reg)
-        }
-    }else{
-        filtered_file_types = vec![
-            Regex::new(r"(?i).json$").unwrap(),
-            Regex::new(r"(?i).md$").unwrap(),
-        ];
-    }
-    //Reads regexes to count as bugs from file
-    let raw_string_recognized_bugfix_indicators = std::fs::read_to_string("regex_recognized_bugfixes.json").unwrap();
-    let parsed_raw_string_recognized_bugfix_indicators: Vec<String> = serde_json::from_str(&raw_string_recognized_bugfix_indicators).unwrap();
-    let mut recognized_bugfix_indicators =vec![];
-    if parsed_raw_string_recognized_bugfix_indicators.len() > 0{
-        for string in parsed_raw_string_recognized_bugfix_indicators{
-            let reg = Regex::new(&string).unwrap();
-            recognized_bugfix_indicators.push(reg);
-        }
-    }else{
-        recognized_bugfix_indicators = vec![
-            Regex::new(r"(?i)line-[0-9]+").unwrap(), //upsales confirmed standard
-            Regex::new(r"(?i)bug").unwrap(),         //older upsales confirmed, might break on other ones
-            Regex::new(r"(?i)hotfix").unwrap(),      //upsales confirmed 2nd standard for speedier fixes
-            Regex::new(r"(?i)fix:").unwrap(),        //confirmed as standard in electron
-            Regex::new(r"(?i)fix(.*):").unwrap(),    //confirmed as standard in vue(v2)
-            Regex::new(r"(?i)bugfix").unwrap(),      //btc
-            Regex::new(r"(?i)[ \n]fix ").unwrap(),   //btc
-        ];
-    }
-
-    let mode: &str = &args[1];
-    match  mode {
-        //exclusivley files, runs multi precentage version of text and anylized the data into averages
-        "multi_analysis"=>{
-            println!("running large multianalysis");
-            // args 2+ :
-            let json_data_path = &args[2];
-            let json_new_file_name = &args[3];
-            let printing_logs_to_file = args.len() > 4;
-
-            let file_string = std::fs::read_to_string(json_data_path).unwrap();
-            let file_data: HashMap<String, Vec<(String, Vec<String>, i32, String)>> = serde_json::from_str(&file_string).unwrap();
-            //This is how much of the repo to include when making a prediction list we make a list of prioritized files for each precentage of the data.
-            let precentages = [5
-            ,10,15,20,25,30,35,40,45,50,55,60,65,70,75];
-             //this is breakpoints for top% of items, so 1 is the top 1% of items sorted by the chosen factor
-            let top_list_precentage_breakpoints = [1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25];//
-            let nr_of_fields = get_implemented_nr_of_fields_for_analysis();
-            let mut final_data_labels = vec![];
-            let mut final_data :Vec<Vec<(i32,Vec<f64>)>> = Vec::new();
-
-            for index in 0..nr_of_fields{
-                final_data_labels.push(get_file_field_name(index));
-                final_data.push(vec![]);
-                for p in &top_list_precentage_breakpoints{
-                    final_data.get_mut(index as usize).unwrap().push((*p, vec![]))
-                }
-            }
-             //DEBUG-log
-            let mut log_file:fs::File;
-            let _ = fs::remove_file(json_new_file_name.to_owned() + "__log");
-            //let _ = fs::File::create(json_new_file_name.to_owned() + "__log");
-            //let mut  log_file = std::fs::File::options().append(true).open().unwrap();
-            log_file = OpenOptions::new().create_new(true).append(true).open(json_new_file_name.to_owned() + "__log").unwrap();
-            if !printing_logs_to_file{
-                //OBS: TODO: this doesnt remove file
-                let _ = fs::remove_file(json_new_file_name.to_owned() + "__log");
-            }
-
-            for precentage in precentages{
-
-                if printing_logs_to_file{
-                    //log_file.write_all(precentage.to_string());
-                    let _ = writeln!(&mut log_file, "{}% of repo", precentage);
-                }
-                let _ = writeln!(&mut log_file, "  #Field");
-
-
-                let age_cuttof_in_precentage_points = precentage as usize;
-
-                let file_list = file_data_map_to_file_list(&file_data, age_cuttof_in_precentage_points.to_owned(), &recognized_bugfix_indicators, &filtered_file_types);
-
-                for i in 0..nr_of_fields{
-
-                    if printing_logs_to_file{
-                        let _ = writeln!(&mut log_file, "  #{}", i);
-                        let _ = writeln!(&mut log_file, "     top->found");
-                    }
-
-                    let field_to_sort_by = i;
-
-                    let mut sortable_file_vec:Vec<&File> = file_list.files.values().into_iter().collect();
-
-                    //sort files by chosen field
-                    sortable_file_vec.sort_by(|a:&&File,b:&&File|{b.get_field(field_to_sort_by).partial_cmp(&a.get_field(field_to_sort_by))}.unwrap());
-
-                    let precentages_to_files = top_list_precentage_breakpoints.map(|i|{ return (sortable_file_vec.len() * i as usize)/100});
-                    let mut breakpoints_total_bugs_predicted:VecDeque<f32> = VecDeque::with_capacity(top_list_precentage_breakpoints.len());
-
-                    let mut precentage_found_count = 0.0;
-                    let mut breakpoint_index = 0;
-                    let mut index = 0;
-                    //currentley only does files
-
-                    for file in sortable_file_vec{
-
-
-                        precentage_found_count += ((file.times_file_got_bugfixed_after_end_of_measuring as f32/file_list.total_bugfixes_after_file_list as f32)*10000.0).round()/100.0;
-
-                        //run check for breakpoints where we list how many % of bugs found
-                        if breakpoint_index < precentages_to_files.len() && index == precentages_to_files[breakpoint_index]{
-                            if printing_logs_to_file{
-                                let _ = writeln!(&mut log_file, "     {}% -> {}%", top_list_precentage_breakpoints.get(breakpoint_index).unwrap(),precentage_found_count);
-                            }
-                            breakpoints_total_bugs_predicted.push_back(precentage_found_count);
-                            breakpoint_index += 1;
+                //bugs this change introduced, only the ones fixed inside the window are known at the cuttof
+                if !file.fixed_by.is_empty() {
+                    let changed_file = file_list.files.get_mut(filename.as_str()).unwrap();
+                    changed_file.bugs_introduced_counter += count_known_fixes(raw_data, &file.fixed_by, train_start_to_int);
+                    for (function, bugs) in count_known_function_fixes(raw_data, &file.function_fixed_by, train_start_to_int) {
+                        if let Some(changed_function) = changed_file.function_list.get_mut(&function) {
+                            changed_function.bugs_introduced_counter += bugs;
                         }
-
-                        index+=1;
-                    }
-
-                    //Push the resuling % of found bugs for each precentage breakpoint(j) for this paticular field(i)
-                    let field_sorted_by_vec_ref= final_data.get_mut(i as usize).unwrap();
-
-                    for j in 0..breakpoints_total_bugs_predicted.len() {
-                        field_sorted_by_vec_ref.get_mut(j).unwrap().1.push(breakpoints_total_bugs_predicted[j] as f64)
-                        //.push(breakpoints_total_bugs_predicted[j] as f64);
-                       // let _ = writeln!(huge_string, "top {}% in list => {}% of bugs predicted", top_list_precentage_breakpoints[j], breakpoints_total_bugs_predicted[j] );
                     }
-
                 }
-
-
-
-            }
-
-
-            let mut movable_indexes:Vec<usize> = vec![];
-            for i in 0..final_data.len(){
-                movable_indexes.push(i)
             }
+        }
+    }
 
-            //Get total deviation from precentages
-            let mut movable_index_divergence_total:HashMap<usize, f64> = HashMap::new();
+    //files that are usually changed together with a file share its bugs
+    let co_change = CoChange::from_raw_data(raw_data, rename_map, filtered_filetypes, train_start_to_int, age_precentage_to_int);
+    let coupling: Vec<(String, f32, f32)> = file_list
+        .files
+        .keys()
+        .map(|filename| {
+            let coupled_files = co_change.get_coupled_files(filename, MIN_SUPPORT, MIN_CONFIDENCE);
+            let coupled_bugs = coupled_files
+                .iter()
+                .filter_map(|coupled_file| file_list.files.get(*coupled_file))
+                .fold(0.0, |bugs, coupled_file| bugs + coupled_file.bug_counter);
+            (filename.to_owned(), coupled_files.len() as f32, coupled_bugs)
+        })
+        .collect();
+    for (filename, coupled_files, coupled_bugs) in coupling {
+        let file = file_list.files.get_mut(&filename).unwrap();
+        file.coupled_files = coupled_files;
+        file.coupled_bug_counter = coupled_bugs;
+    }
+    file_list
+}
 
-            for (i,_/*_=label*/) in final_data_labels.iter().enumerate(){
 
-                movable_index_divergence_total.insert(i.to_owned(), 0.0);
-                if let Some(title_vector) = final_data.get(i){
-                    for precentage_pair in title_vector{
-                        let avg_sum:f64 = precentage_pair.1.iter().sum();
-                        let avg = avg_sum/(precentage_pair.1.len() as f64);
-                        *movable_index_divergence_total.get_mut(&i.to_owned()).unwrap() += (avg - precentage_pair.0 as f64)/top_list_precentage_breakpoints.len() as f64 ;
-                    }
-                }
-            }
+//Share (in %) of the bugs fixed after the cuttof that are in the top top_precentage % of the files ranked by factor
+fn get_precentage_of_bugs_found(file_list: &FileList, factor: &Factor, top_precentage: usize) -> f32 {
+    let mut sortable_file_vec: Vec<(f32, &File)> = file_list.files.values().map(|file| (file.get_factor_value(factor), file)).collect();
+    sortable_file_vec.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let top_files = sortable_file_vec.len() * top_precentage / 100;
+    let found_bugs: i32 = sortable_file_vec
+        .iter()
+        .take(top_files)
+        .map(|(_, file)| file.times_file_got_bugfixed_after_end_of_measuring)
+        .sum();
+    found_bugs as f32 / file_list.total_bugfixes_after_file_list as f32 * 100.0
+}
 
-            //sort index_vector for use in sorting other indexed vectors
-            movable_indexes.sort_by(|a,b|{
+//This filters out all files matching filtered_filetypes from the raw data //TODO-do remove other places where i use filter or remove this place.
+fn filter_parsed_diffs(mut raw_data: RawData, filtered_file_types: &[Regex]) -> RawData {
+    for commit in raw_data.commits.values_mut() {
+        commit
+            .files
+            .retain(|file| !filtered_file_types.iter().any(|filter| filter.is_match(&file.path)));
+    }
+    raw_data
+}
 
-                //b.get_field(field_to_sort_by).partial_cmp(&a.get_field(field_to_sort_by))
-                let b_div = movable_index_divergence_total.get(b).unwrap();
-                let a_div = movable_index_divergence_total.get(a).unwrap();
 
-                return b_div.partial_cmp(&a_div).unwrap();
+//This is how much of the repo to include when making a prediction list, multi_analysis makes a list of prioritized files for each precentage of the data.
+const MULTI_ANALYSIS_CUTTOFS: [usize; 15] = [5,10,15,20,25,30,35,40,45,50,55,60,65,70,75];
+//this is breakpoints for top% of items, so 1 is the top 1% of items sorted by the chosen factor
+const MULTI_ANALYSIS_BREAKPOINTS: [f64; 25] = [1.0,2.0,3.0,4.0,5.0,6.0,7.0,8.0,9.0,10.0,11.0,12.0,13.0,14.0,15.0,16.0,17.0,18.0,19.0,20.0,21.0,22.0,23.0,24.0,25.0];
+const TEXT_BREAKPOINTS: [f64; 6] = [1.0, 5.0, 10.0, 25.0, 50.0, 75.0];
+
+//Ranks the files by every factor at every cuttof (or window) and measures how many of the bugs fixed after the cuttof are in the top of the list.
+//log gets every cuttof/factor/breakpoint result, pass io::sink() to skip it
+fn analyze_factors(
+    raw_data: &RawData,
+    factors: &[Factor],
+    grid: &EvaluationGrid,
+    counting: &CountingOptions,
+    log: &mut dyn std::io::Write,
+) -> MultiAnalysisReport {
+    let breakpoints = &grid.breakpoints;
+    //factor -> breakpoint -> (bugs found, % of the files looked at) per cuttof
+    let mut final_data :Vec<Vec<Vec<(f64, f64)>>> = vec![vec![vec![]; breakpoints.len()]; factors.len()];
+    //factor -> cuttof -> bugs found per breakpoint and metrics
+    let mut final_cuttof_results :Vec<Vec<CuttofAnalysis>> = (0..factors.len()).map(|_| vec![]).collect();
+
+    for window in &grid.windows{
+
+        let _ = writeln!(log, "{} of repo", window);
+        let _ = writeln!(log, "  #Field");
+
+        let file_list = file_data_map_to_file_list(raw_data, *window, counting);
+
+        for (i, factor_to_sort_by) in factors.iter().enumerate(){
+
+            let _ = writeln!(log, "  #{}", i);
+            let _ = writeln!(log, "     top->found");
+
+            let mut sortable_file_vec:Vec<(f32, &File)> = file_list.files.values().map(|file| (file.get_factor_value(factor_to_sort_by), file)).collect();
+
+            //sort files by chosen factor
+            sortable_file_vec.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+            //files without lines (deleted, or raw data from before loc was saved) still take some effort to look at
+            let ranked_files: Vec<RankedFile> = sortable_file_vec.iter().map(|(score, file)| RankedFile {
+                score: *score,
+                bugs: file.times_file_got_bugfixed_after_end_of_measuring as f32,
+                loc: file.loc.max(1) as f32,
+            }).collect();
+            let metrics = Metrics::new(&ranked_files);
+
+            //% of the bugs found after looking at every file in the ranking
+            let mut precentage_found_count = 0.0;
+            let found_counts: Vec<f32> = sortable_file_vec.iter().map(|(_, file)| {
+                precentage_found_count += ((file.times_file_got_bugfixed_after_end_of_measuring as f32/file_list.total_bugfixes_after_file_list as f32)*10000.0).round()/100.0;
+                precentage_found_count
+            }).collect();
+
+            //without bugs fixed after the cuttof (like with a short horizon) nothing can be found, the window is left out of the averages
+            let bugs_found: Vec<Option<f32>> = if file_list.total_bugfixes_after_file_list == 0 {
+                vec![None; breakpoints.len()]
+            } else {
+                breakpoints.iter().map(|breakpoint| breakpoint.get_file_index(found_counts.len()).map(|index| found_counts[index])).collect()
+            };
+            let mut deviations = vec![];
+            for (j, breakpoint) in breakpoints.iter().enumerate() {
+                if let Some(found) = bugs_found[j] {
+                    let _ = writeln!(log, "     {} -> {}%", breakpoint, found);
+                    let share_of_files = breakpoint.get_share_of_files(found_counts.len());
+                    //Push the resuling % of found bugs for each breakpoint(j) for this paticular field(i)
+                    final_data[i][j].push((found as f64, share_of_files));
+                    deviations.push(found as f64 - share_of_files);
                 }
-            );
-            //println!("{}", serde_json::to_string_pretty(&movable_indexes).unwrap());
-            let mut huge_string:String = String::new();
-
-            for moved_index in movable_indexes{
-                let _ = writeln!(huge_string,"{} > avg deviation = {}", final_data_labels.get(moved_index).unwrap(), movable_index_divergence_total.get(&moved_index).unwrap());
             }
+            final_cuttof_results[i].push(CuttofAnalysis {
+                window: *window,
+                bugs_found,
+                deviation: deviations.iter().sum::<f64>() / deviations.len() as f64,
+                metrics,
+            });
+        }
+    }
 
+    //Get total deviation from the share of files looked at, averaged over the breakpoints any cuttof reached (a top_n larger than the repo reaches none)
+    let average = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+    let divergence_total: Vec<f64> = final_data.iter().map(|factor_data| {
+        average(factor_data.iter().filter(|found| !found.is_empty()).map(|found| average(found.iter().map(|(found, share)| found - share).collect())).collect())
+    }).collect();
+
+    //sort index_vector for use in sorting other indexed vectors, factors without a deviation (NaN) last
+    let sort_value = |i: usize| if divergence_total[i].is_nan() { f64::NEG_INFINITY } else { divergence_total[i] };
+    let mut movable_indexes:Vec<usize> = (0..factors.len()).collect();
+    movable_indexes.sort_by(|a,b| sort_value(*b).total_cmp(&sort_value(*a)));
+
+    let final_average_metrics: Vec<Metrics> = final_cuttof_results.iter().map(|cuttof_results| {
+        let cuttof_metrics: Vec<Metrics> = cuttof_results.iter().map(|result| result.metrics.clone()).collect();
+        Metrics::average(&cuttof_metrics)
+    }).collect();
+
+    //every cuttof is an observation for the significance tests
+    let cuttof_deviations: Vec<Vec<f64>> = final_cuttof_results.iter().map(|cuttof_results| cuttof_results.iter().map(|result| result.deviation).collect()).collect();
+    let cuttof_aucs: Vec<Vec<f64>> = final_cuttof_results.iter().map(|cuttof_results| cuttof_results.iter().map(|result| result.metrics.auc).collect()).collect();
+    let statistics = compare_factors(&cuttof_deviations, &cuttof_aucs, &movable_indexes);
+
+    MultiAnalysisReport {
+        windows: grid.windows.clone(),
+        breakpoints: breakpoints.clone(),
+        ranking: movable_indexes.iter().map(|i| factors[*i].slug.to_owned()).collect(),
+        factors: factors.iter().zip(final_cuttof_results).enumerate().map(|(i, (factor, cuttof_results))| FactorAnalysis {
+            slug: factor.slug.to_owned(),
+            name: factor.name.to_owned(),
+            rank: movable_indexes.iter().position(|index| *index == i).unwrap() + 1,
+            average_deviation: divergence_total[i],
+            average_bugs_found: final_data[i].iter().map(|found| average(found.iter().map(|(found, _)| *found).collect())).collect(),
+            average_metrics: final_average_metrics[i].clone(),
+            statistics: statistics[i].clone(),
+            cuttofs: cuttof_results,
+        }).collect(),
+    }
+}
 
-            //below code is old and should be incorporated with sortablble_indexes, right now we simply print all fo the big data below teh neer metadata
-
-            for (i,label) in final_data_labels.iter().enumerate(){
-
-                let _ = writeln!(huge_string,"{}", label);
-                if let Some(title_vector) = final_data.get(i){
-                    for precentage_pair in title_vector{
-                        let avg_sum:f64 = precentage_pair.1.iter().sum();
-                        let avg = avg_sum/(precentage_pair.1.len() as f64);
-                        let _ = writeln!(huge_string,"  {} => {}", &precentage_pair.0, avg);
-                    }
-                }
-
-
-            }
-
-            let _ = fs::remove_file(json_new_file_name.to_owned() + "__macro_analysis.txt");
-            let mut file = fs::File::create(json_new_file_name.to_owned() + "__macro_analysis.txt").unwrap();
-            file.write_all(huge_string.as_bytes()).unwrap();
+fn main() {
+    let cli = Cli::parse();
+    if let Command::Completions { shell } = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "gitdiffjson", &mut std::io::stdout());
+        return;
+    }
+    let config = or_usage_error(Config::load(cli.config, cli.profile));
+
+    //Regexes to filter, from the config, "regex_filtered_file_types.json" or these
+    let filtered_file_types = or_usage_error(config.get_filtered_file_types(&[
+        r"(?i).json$",
+        r"(?i).md$",
+    ]));
+    //Regexes to count as bugs, from the config, "regex_recognized_bugfixes.json" or these
+    let recognized_bugfix_indicators = or_usage_error(config.get_recognized_bugfix_indicators(&[
+        r"(?i)line-[0-9]+", //upsales confirmed standard
+        r"(?i)bug",         //older upsales confirmed, might break on other ones
+        r"(?i)hotfix",      //upsales confirmed 2nd standard for speedier fixes
+        r"(?i)fix:",        //confirmed as standard in electron
+        r"(?i)fix(.*):",    //confirmed as standard in vue(v2)
+        r"(?i)bugfix",      //btc
+        r"(?i)[ \n]fix ",   //btc
+    ]));
+
+    //Reads user defined function extractors from file if it exists, built in ones are used for the rest
+    let function_extractors = or_usage_error(load_function_extractors("regex_function_extractors.json"));
+    //Factors to rank files by, the defaults plus the formulas in factors.json and the config
+    let mut factors = or_usage_error(load_factors("factors.json"));
+    or_usage_error(add_factor_entries(&mut factors, config.get_factor_entries(), "the config"));
+
+    match cli.command {
+        //exclusivley files, runs multi precentage version of text and anylized the data into averages
+        Command::MultiAnalysis { raw_data: json_data_path, output, logs: printing_logs_to_file, analysis, grid: grid_args, format } =>{
+            println!("running large multianalysis");
+            let options = config.with_options("multi_analysis", [analysis.to_options(), grid_args.to_options(), format.to_options()].concat());
+            let json_new_file_name = &config.get_output_path(&output);
+            let mut age_options = or_usage_error(parse_age_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
+            let output_format = or_usage_error(parse_output_format(&options));
+            let grid = or_usage_error(parse_evaluation_grid(&options, &MULTI_ANALYSIS_CUTTOFS, &MULTI_ANALYSIS_BREAKPOINTS));
+
+            let mut raw_data = read_raw_data(&json_data_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            or_usage_error(check_ground_truth(ground_truth, &raw_data));
+            let rename_map = RenameMap::new(&raw_data);
+            apply_age_options(&mut raw_data, &mut age_options);
+            or_usage_error(check_horizon(grid.horizon, &age_options));
+            let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
+
+            //DEBUG-log
+            let _ = fs::remove_file(json_new_file_name.to_owned() + "__log");
+            let mut log: Box<dyn std::io::Write> = if printing_logs_to_file {
+                Box::new(OpenOptions::new().create_new(true).append(true).open(json_new_file_name.to_owned() + "__log").unwrap())
+            } else {
+                Box::new(std::io::sink())
+            };
+            let report = analyze_factors(&raw_data, &factors, &grid, &counting, &mut log);
+
+            let report_file_name = json_new_file_name.to_owned() + "__macro_analysis." + output_format.get_extension();
+            let _ = fs::remove_file(&report_file_name);
+            let mut file = fs::File::create(&report_file_name).unwrap();
+            let output = match output_format {
+                OutputFormat::Text => report.to_text(),
+                OutputFormat::Json => serde_json::to_string_pretty(&report).unwrap(),
+                OutputFormat::Csv => report.to_csv(),
+            };
+            file.write_all(output.as_bytes()).unwrap();
         },
         //generate more compact textfile from raw data(generated by "repo")
-        "text" =>{
+        Command::Text { raw_data: path, output, cutoff, analysis, grid, format } =>{
             println!("generate compact textfile");
-            // args 2+ :
-            let path = &args[2];
-            let filename = &args[3];
-            let age_cuttof_in_precentage_points:&usize = &args[4].parse::<usize>().unwrap() ;
+            let options = config.with_options("text", [analysis.to_options(), grid.to_options(), format.to_options()].concat());
+            let filename = &config.get_output_path(&output);
+            let age_cuttof_in_precentage_points:&usize = &cutoff;
+            let mut age_options = or_usage_error(parse_age_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
+            let output_format = or_usage_error(parse_output_format(&options));
+            //only the breakpoints and the horizon are used, the cuttof is --cutoff
+            let text_grid = or_usage_error(parse_evaluation_grid(&options, &[*age_cuttof_in_precentage_points], &TEXT_BREAKPOINTS));
+            let top_list_precentage_breakpoints = text_grid.breakpoints;
 
-            let nr_of_fields = get_implemented_nr_of_fields_for_analysis();
             let mut huge_string:String = String::new();
+            let mut factor_breakpoints = vec![];
 
-            let file_string = std::fs::read_to_string(path).unwrap();
+            let mut raw_data = read_raw_data(&path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            or_usage_error(check_ground_truth(ground_truth, &raw_data));
+            let rename_map = RenameMap::new(&raw_data);
+            apply_age_options(&mut raw_data, &mut age_options);
+            or_usage_error(check_horizon(text_grid.horizon, &age_options));
+            let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
 
-            let file_data: HashMap<String, Vec<(String, Vec<String>, i32, String)>> = serde_json::from_str(&file_string).unwrap();
+            let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow { horizon: text_grid.horizon, ..EvaluationWindow::from_cuttof(*age_cuttof_in_precentage_points) }, &counting);
 
-            let file_list = file_data_map_to_file_list(&file_data, age_cuttof_in_precentage_points.to_owned(), &recognized_bugfix_indicators, &filtered_file_types);
 
+            for factor_to_sort_by in &factors{
 
-            for i in 0..nr_of_fields{
-                let field_to_sort_by = i;
+                let _ = writeln!(huge_string, "----- {} -----", factor_to_sort_by.name);
 
-                let _ = writeln!(huge_string, "----- {} -----", get_file_field_name(field_to_sort_by));
+                let mut sortable_file_vec:Vec<(f32, &File)> = file_list.files.values().map(|file| (file.get_factor_value(factor_to_sort_by), file)).collect();
 
-                let mut sortable_file_vec:Vec<&File> = file_list.files.values().into_iter().collect();
-
-                //sort files by chosen field
-                sortable_file_vec.sort_by(|a:&&File,b:&&File|{b.get_field(field_to_sort_by).partial_cmp(&a.get_field(field_to_sort_by))}.unwrap());
-
-
-                let top_list_precentage_breakpoints = [1, 5, 10, 25, 50, 75];
-                let precentages_to_files = top_list_precentage_breakpoints.map(|i|{ return (sortable_file_vec.len() * i)/100});
-                let mut breakpoints_total_bugs_predicted:VecDeque<f32> = VecDeque::with_capacity(top_list_precentage_breakpoints.len());
+                //sort files by chosen factor
+                sortable_file_vec.sort_by(|a, b| b.0.total_cmp(&a.0));
 
                 let mut precentage_found_count = 0.0;
-                let mut breakpoint_index = 0;
-                let mut index = 0;
                 //currentley only does files
-                for file in sortable_file_vec{
-
+                let found_counts: Vec<f32> = sortable_file_vec.iter().map(|(_, file)| {
                     precentage_found_count += ((file.times_file_got_bugfixed_after_end_of_measuring as f32/file_list.total_bugfixes_after_file_list as f32)*10000.0).round() / 100.0;
+                    precentage_found_count
+                }).collect();
 
-                    //run check for breakpoints where we list how many % of bugs found
-                    if breakpoint_index < precentages_to_files.len() && index == precentages_to_files[breakpoint_index]{
-                        breakpoints_total_bugs_predicted.push_back(precentage_found_count);
-                        breakpoint_index += 1;
-                    }
-
-                    index+=1;
-                }
-
+                //how many % of bugs found at the breakpoints
+                let breakpoints_total_bugs_predicted: Vec<Option<f32>> = top_list_precentage_breakpoints.iter().map(|breakpoint| breakpoint.get_file_index(found_counts.len()).map(|index| found_counts[index])).collect();
 
-                for i in 0..breakpoints_total_bugs_predicted.len() {
-                    let _ = writeln!(huge_string, "top {}% in list => {}% of bugs predicted", top_list_precentage_breakpoints[i], breakpoints_total_bugs_predicted[i] );
+                for (breakpoint, found) in top_list_precentage_breakpoints.iter().zip(&breakpoints_total_bugs_predicted) {
+                    if let Some(found) = found {
+                        match breakpoint {
+                            Breakpoint::Precentage(precentage) => { let _ = writeln!(huge_string, "top {}% in list => {}% of bugs predicted", precentage, found); },
+                            Breakpoint::Count(count) => { let _ = writeln!(huge_string, "top {} files in list => {}% of bugs predicted", count, found); },
+                        }
+                    }
                 }
 
 
                 let _ = writeln!(huge_string, "\n");
+                factor_breakpoints.push(FactorBreakpoints {
+                    slug: factor_to_sort_by.slug.to_owned(),
+                    name: factor_to_sort_by.name.to_owned(),
+                    bugs_found: breakpoints_total_bugs_predicted,
+                });
             }
 
-            let _ = fs::remove_file(filename.to_owned() + "_fileMap.txt");
-            let mut file = fs::File::create(filename.to_owned() + "_fileMap.txt").unwrap();
+            let report = TextReport {
+                cuttof: *age_cuttof_in_precentage_points,
+                breakpoints: top_list_precentage_breakpoints,
+                factors: factor_breakpoints,
+            };
+            let output = match output_format {
+                OutputFormat::Text => huge_string,
+                OutputFormat::Json => serde_json::to_string_pretty(&report).unwrap(),
+                OutputFormat::Csv => report.to_csv(),
+            };
+            let report_file_name = filename.to_owned() + "_fileMap." + output_format.get_extension();
+            let _ = fs::remove_file(&report_file_name);
+            let mut file = fs::File::create(&report_file_name).unwrap();
 
-            file.write_all(huge_string.as_bytes()).unwrap();
+            file.write_all(output.as_bytes()).unwrap();
 
         }
 
         //generate raw data from git repo
-        "repo" =>{
+        Command::Repo { repo_path, repo, bugfix } =>{
             println!(" generate raw data from git repo");
-            // args 2+ :
-            let directory_path = &args[2];
-            let sha_to_parsed_diffs = generate_json(&directory_path);
-
-            let mut result = HashMap::new();
-            for (sha, parsed_diffs) in sha_to_parsed_diffs {
-                //This  filters out all files matching filtered_filetypes //TODO-do remove other places where i use filter or remove this place.
-                let mut filtered_diffs = vec![];
-                for item in parsed_diffs{
-                    let mut is_valid_item = true;
-                    for filter in &filtered_file_types{
-                        if filter.is_match(&item.0){
-                            is_valid_item = false;
-                            break;
-                        }
-                    }
-                    if is_valid_item{
-                        filtered_diffs.push(item)
-                    }
-
-                }
-                result.insert(sha, filtered_diffs);
+            let options = config.with_options("repo", [repo.to_options(), bugfix.to_options()].concat());
+            let directory_path = &repo_path;
+            let repo_options = or_usage_error(parse_repo_options(&options));
+            //parsed before the history is walked so invalid options are found right away
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let raw_data = generate_json(directory_path, &repo_options, &function_extractors);
+
+            let mut result = filter_parsed_diffs(raw_data, &filtered_file_types);
+            if repo_options.szz {
+                find_bug_introducing_commits(directory_path, &mut result, &bugfix_classifier, &filtered_file_types, &function_extractors);
+            }
 
+            write_raw_data(&config.get_output_path(DEFAULT_RAW_DATA), &result);
+            }
+        ,
+        //update existing raw data with only the commits that are not already in it
+        Command::RepoIncremental { repo_path, raw_data, repo, bugfix } =>{
+            println!(" update raw data with new commits from git repo");
+            let options = config.with_options("repo_incremental", [repo.to_options(), bugfix.to_options()].concat());
+            let directory_path = &repo_path;
+            let repo_options = or_usage_error(parse_repo_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let existing_json_path = &raw_data.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA));
+
+            //old format raw data is converted on read, so it is upgraded to the current format here
+            let existing_data = read_raw_data(existing_json_path);
+            //new bugfixes can blame old commits, so the bug introducing commits are all found again
+            let szz = repo_options.szz || existing_data.bug_introducing;
+
+            let raw_data = generate_json_incremental(directory_path, existing_data, &repo_options, &function_extractors);
+
+            let mut result = filter_parsed_diffs(raw_data, &filtered_file_types);
+            if szz {
+                find_bug_introducing_commits(directory_path, &mut result, &bugfix_classifier, &filtered_file_types, &function_extractors);
             }
 
-            let json = serde_json::to_string_pretty(&result).unwrap();
-            let mut file = fs::File::create("generatedJson.json").unwrap();
-            file.write_all(json.as_bytes()).unwrap();
+            //updated in place, like "szz" does
+            write_raw_data(existing_json_path, &result);
+            }
+        ,
+        //find the commits that introduced the bugs of the bugfixes in existing raw data (SZZ) and save them in it
+        Command::Szz { repo_path, raw_data, bugfix } =>{
+            println!(" find bug introducing commits");
+            let options = config.with_options("szz", bugfix.to_options());
+            let directory_path = &repo_path;
+            let json_path = &raw_data.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+
+            let mut raw_data = read_raw_data(json_path);
+            find_bug_introducing_commits(directory_path, &mut raw_data, &bugfix_classifier, &filtered_file_types, &function_extractors);
+
+            write_raw_data(json_path, &raw_data);
             }
         ,
-        //OBS: this function has deadcode from original purpose, args 2 full/files sub-mode only works with "files"
+        //check the bugfix classifier against hand labelled commits, precision, recall and the misclassified messages of it and of every regex
+        Command::BugfixQuality { raw_data: json_path, output, labels: labels_path, bugfix, format } =>{
+            println!(" measure the bugfix classifier against labelled commits");
+            let options = config.with_options("bugfix_quality", [bugfix.to_options(), format.to_options()].concat());
+            let new_file_name = &config.get_output_path(&output);
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let output_format = or_usage_error(parse_output_format(&options));
+
+            let raw_data = read_raw_data(&json_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            let labels = or_usage_error(read_labels(&labels_path));
+            let report = or_usage_error(get_bugfix_quality(&raw_data, &labels, &bugfix_classifier, &recognized_bugfix_indicators));
+
+            let report_file_name = new_file_name.to_owned() + "__bugfix_quality." + output_format.get_extension();
+            let _ = fs::remove_file(&report_file_name);
+            let mut file = fs::File::create(&report_file_name).unwrap();
+            let output = match output_format {
+                OutputFormat::Text => report.to_text(),
+                OutputFormat::Json => serde_json::to_string_pretty(&report).unwrap(),
+                OutputFormat::Csv => report.to_csv(),
+            };
+            file.write_all(output.as_bytes()).unwrap();
+            }
+        ,
+        //OBS: this function has deadcode from original purpose, --view only works with "files"
         //Convert raw extracted data into d3 treemap parsable jsons for entire folder structure
-        "d3"=>{
+        Command::D3 { raw_data: json_path, output, view, factor, items, analysis } =>{
             println!("Convert file/function objects into d3 treemap parsable json");
-            // args 2+ :
-            let json_path = &args[2];
-            let new_filename = &args[3];
-            let sub_mode:&str = &args[4];
-            let field_to_analyze = &args[5].parse::<usize>().unwrap();
+            let options = config.with_options("d3", analysis.to_options());
+            let new_filename = &config.get_output_path(&output);
+            let sub_mode:&str = &view;
+            //slug or index of the factor
+            let field_to_analyze = &or_usage_error(find_factor(&factors, &factor).ok_or(format!("unknown factor {}, the \"factors\" subcommand lists them", factor)));
             //This is amount of items specificly in the page showing just individual files
-            let amount_items_to_show:usize = args[6].parse::<usize>().unwrap();
-
-            let file_string = std::fs::read_to_string(json_path).unwrap();
-
-            let file_data: HashMap<String, Vec<(String, Vec<String>, i32, String)>> = serde_json::from_str(&file_string).unwrap();
-
-
-            let file_list = file_data_map_to_file_list(&file_data, 100, &recognized_bugfix_indicators, &filtered_file_types);
+            let amount_items_to_show:usize = items;
+            let mut age_options = or_usage_error(parse_age_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
+
+            let mut raw_data = read_raw_data(&json_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            or_usage_error(check_ground_truth(ground_truth, &raw_data));
+            let rename_map = RenameMap::new(&raw_data);
+            apply_age_options(&mut raw_data, &mut age_options);
+            let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
+
+            let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow::from_cuttof(100), &counting);
             //file_list.files.get(name) gives object from full filepath
             let mut container : Container ;
 
             match sub_mode {
                 "files"=>{
-                    container = filelist_to_container_only_files(&file_list, *field_to_analyze as i32);
+                    container = filelist_to_container_only_files(&file_list, &factors[*field_to_analyze]);
                     container.sort_parents_by_total_child_value();}
                 "full"=> {
                     //OBS: TODO this takes forever to run and sometimes crashes because of memory, TODO: trash this and do partial ones
-                    container = filelist_to_container(file_list, field_to_analyze.to_owned() as i32);
+                    container = filelist_to_container(file_list, &factors[*field_to_analyze], field_to_analyze.to_owned() as i32);
                     container.sort_parents_by_total_child_value();}
 
                 _=> {println!("no matching field for for {} ", sub_mode); return}
@@ -1348,7 +1966,7 @@ fn main() {
                     parts.pop();
                     let mut path = "".to_string();
                     for part in &parts{
-                        if path != ""{
+                        if !path.is_empty(){
                             path += "/";
                         }
                         path += part;
@@ -1376,10 +1994,10 @@ fn main() {
 
                 let mut filteredpath =  path.clone() ;
                 if  filteredpath.starts_with("."){
-                    filteredpath = (&path[1..]).to_string();
+                    filteredpath = path[1..].to_string();
 
                 }
-                else if  filteredpath == ""{
+                else if  filteredpath.is_empty(){
                     filteredpath = "root".to_string();
                 }
 
@@ -1409,24 +2027,423 @@ fn main() {
 
         }
         //Parse raw data into file/function objects
-        "classes" =>{
+        Command::Classes { raw_data: json_path, output, cutoff: age_cuttof, analysis } =>{
             println!("Parse raw data into file/function objects ");
-            // args 2+ :
-            let json_path = &args[2];
-            let new_filename = &args[3];
-            let age_cuttof_precentage = &args[4].parse::<i128>().unwrap() ;
-            let age_cuttof:usize = *age_cuttof_precentage as usize;
+            let options = config.with_options("classes", analysis.to_options());
+            let new_filename = &config.get_output_path(&output);
+            let mut age_options = or_usage_error(parse_age_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
 
-            let file_string = std::fs::read_to_string(json_path).unwrap();
-            let file_data: HashMap<String, Vec<(String, Vec<String>, i32, String)>> = serde_json::from_str(&file_string).unwrap();
+            let mut raw_data = read_raw_data(&json_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            or_usage_error(check_ground_truth(ground_truth, &raw_data));
+            let rename_map = RenameMap::new(&raw_data);
+            apply_age_options(&mut raw_data, &mut age_options);
+            let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
 
-            let file_list = file_data_map_to_file_list(&file_data, age_cuttof, &recognized_bugfix_indicators, &filtered_file_types);
+            let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow::from_cuttof(age_cuttof), &counting);
 
             let json = serde_json::to_string_pretty(&file_list).unwrap();
             let mut file = fs::File::create(new_filename.to_owned() + ".json").unwrap();
             file.write_all(json.as_bytes()).unwrap();
         }
         ,
-        _=> println!("no matching branch for {} argument", args[1])
+        //Runs the multi_analysis evaluation on several repositories (or reads their results) and ranks the factors over all of them
+        Command::Aggregate { paths, output, analysis, grid, format } =>{
+            println!("aggregate factor results of several repositories");
+            let options = config.with_options("aggregate", [analysis.to_options(), grid.to_options(), format.to_options()].concat());
+            let new_filename = &config.get_output_path(&output);
+            let output_format = or_usage_error(parse_output_format(&options));
+            let grid = or_usage_error(parse_evaluation_grid(&options, &MULTI_ANALYSIS_CUTTOFS, &MULTI_ANALYSIS_BREAKPOINTS));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
+            let repo_names = or_usage_error(get_repo_names(&paths));
+            let mut results: Vec<RepoResult> = vec![];
+            for (path, repo_name) in paths.iter().zip(&repo_names) {
+                println!("  {}", path);
+                let result = match read_result_file(path, repo_name, &factors) {
+                    Some(result) => result,
+                    None => {
+                        let mut age_options = or_usage_error(parse_age_options(&options));
+                        let mut raw_data = read_raw_data(path);
+                        or_usage_error(check_ground_truth(ground_truth, &raw_data));
+                        let rename_map = RenameMap::new(&raw_data);
+                        apply_age_options(&mut raw_data, &mut age_options);
+                        or_usage_error(check_horizon(grid.horizon, &age_options));
+                        let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
+                        let report = analyze_factors(&raw_data, &factors, &grid, &counting, &mut std::io::sink());
+                        RepoResult::from_report(repo_name, &report)
+                    }
+                };
+                results.push(result);
+            }
+            if results.is_empty() {
+                usage_error("aggregate needs raw data or multi_analysis result files to combine".to_string());
+            }
+
+            let report = aggregate(&results);
+            let output = match output_format {
+                OutputFormat::Text => report.to_text(),
+                OutputFormat::Json => serde_json::to_string_pretty(&report).unwrap(),
+                OutputFormat::Csv => report.to_csv(),
+            };
+            let report_file_name = new_filename.to_owned() + "__aggregate." + output_format.get_extension();
+            let mut file = fs::File::create(&report_file_name).unwrap();
+            file.write_all(output.as_bytes()).unwrap();
+            println!("wrote {}", report_file_name);
+        }
+        ,
+        //Fit a linear or logistic combination of the file counters on early cuttofs, test it on later ones and save it as a factor
+        Command::Fit { raw_data: json_path, output, model, train: train_cuttofs, test: test_cuttofs, factors_out, analysis } =>{
+            println!("fit a factor on earlier cuttofs");
+            let options = config.with_options("fit", analysis.to_options());
+            let new_filename = &config.get_output_path(&output);
+            let model = if model == "logistic" { Model::Logistic } else { Model::Linear };
+            let mut age_options = or_usage_error(parse_age_options(&options));
+            let bugfix_classifier = or_usage_error(parse_bugfix_classifier(&options, &recognized_bugfix_indicators));
+            let ground_truth = or_usage_error(parse_ground_truth(&options));
+
+            let mut raw_data = read_raw_data(&json_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            or_usage_error(check_ground_truth(ground_truth, &raw_data));
+            let rename_map = RenameMap::new(&raw_data);
+            apply_age_options(&mut raw_data, &mut age_options);
+            let counting = CountingOptions { bugfix_classifier: &bugfix_classifier, ground_truth, filtered_filetypes: &filtered_file_types, rename_map: &rename_map, age_options: &age_options };
+
+            //every file of every training cuttof is a sample, labeled with the bugs fixed in it after the cuttof
+            let feature_variables = get_feature_variables();
+            let mut samples: Vec<(Vec<f32>, f32)> = vec![];
+            for cuttof in &train_cuttofs {
+                let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow::from_cuttof(*cuttof), &counting);
+                for file in file_list.files.values() {
+                    let features = feature_variables.iter().map(|(_, variable)| file.get_variable(*variable)).collect();
+                    samples.push((features, file.times_file_got_bugfixed_after_end_of_measuring as f32));
+                }
+            }
+            let fitted_model = fit(model, &samples);
+
+            let model_name = if model == Model::Linear { "linear" } else { "logistic" };
+            let feature_names: Vec<&str> = feature_variables.iter().map(|(name, _)| *name).collect();
+            let fitted_factor = Factor::new(
+                &("fitted_".to_owned() + model_name),
+                &("fitted ".to_owned() + model_name),
+                &format!("{} combination fitted on the {:?}% cuttofs", model_name, train_cuttofs),
+                &fitted_model.to_formula(&feature_names),
+            ).unwrap();
+            println!("fitted formula: {}", fitted_factor.formula);
+
+            //compare it to the other factors on the test cuttofs, cuttofs without bugs after them can not be scored
+            let top_list_precentage_breakpoints = [10, 20];
+            let mut compared_factors: Vec<&Factor> = factors.iter().filter(|factor| factor.slug != fitted_factor.slug).collect();
+            compared_factors.push(&fitted_factor);
+            let mut average_bugs_found = vec![[0.0_f32; 2]; compared_factors.len()];
+            let mut scored_cuttofs = 0;
+            for cuttof in &test_cuttofs {
+                let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow::from_cuttof(*cuttof), &counting);
+                if file_list.total_bugfixes_after_file_list == 0 {
+                    continue;
+                }
+                scored_cuttofs += 1;
+                for (i, factor) in compared_factors.iter().enumerate() {
+                    for (j, breakpoint) in top_list_precentage_breakpoints.iter().enumerate() {
+                        average_bugs_found[i][j] += get_precentage_of_bugs_found(&file_list, factor, *breakpoint);
+                    }
+                }
+            }
+            for bugs_found in &mut average_bugs_found {
+                for value in bugs_found.iter_mut() {
+                    *value /= scored_cuttofs.max(1) as f32;
+                }
+            }
+
+            let mut ranking: Vec<usize> = (0..compared_factors.len()).collect();
+            ranking.sort_by(|a, b| average_bugs_found[*b][1].total_cmp(&average_bugs_found[*a][1]));
+            println!("average % of bugs found in the top 10% / 20% of files on the test cuttofs:");
+            for (place, i) in ranking.iter().enumerate() {
+                if place < 5 || compared_factors[*i].slug == fitted_factor.slug {
+                    println!("  {}. {} => {}% / {}%", place + 1, compared_factors[*i].slug, average_bugs_found[*i][0], average_bugs_found[*i][1]);
+                }
+            }
+
+            let result = FitResult {
+                slug: fitted_factor.slug.to_owned(),
+                formula: fitted_factor.formula.to_owned(),
+                model: model_name.to_string(),
+                train_cuttofs,
+                test_cuttofs,
+                evaluation: ranking.iter().map(|i| FitEvaluation {
+                    slug: compared_factors[*i].slug.to_owned(),
+                    top_10: average_bugs_found[*i][0],
+                    top_20: average_bugs_found[*i][1],
+                }).collect(),
+            };
+            let mut file = fs::File::create(new_filename.to_owned() + "_fit.json").unwrap();
+            file.write_all(serde_json::to_string_pretty(&result).unwrap().as_bytes()).unwrap();
+
+            //only saved when asked for, "factors.json" changes the factors of every later run
+            match factors_out {
+                Some(factors_path) => {
+                    save_factor(&factors_path, &fitted_factor);
+                    println!("saved as factor {} in {}", fitted_factor.slug, factors_path);
+                }
+                None => println!("not saved as a factor, add --factors-out factors.json to use it in the other subcommands"),
+            }
+        }
+        ,
+        //Print all factors with their slug, index, description and formula as json
+        Command::Factors =>{
+            println!("{}", get_factor_registry_json(&factors));
+        }
+        ,
+        //Write which files change together as a graph, and optionally print the files most coupled to one file
+        Command::Coupling { raw_data: json_path, output, min_support, min_confidence, file: coupled_to, top } =>{
+            println!("Generate co-change coupling graph");
+            let new_filename = &config.get_output_path(&output);
+
+            let raw_data = read_raw_data(&json_path.unwrap_or_else(|| config.get_output_path(DEFAULT_RAW_DATA)));
+            let rename_map = RenameMap::new(&raw_data);
+            let co_change = CoChange::from_raw_data(&raw_data, &rename_map, &filtered_file_types, i32::MIN, i32::MAX);
+
+            if let Some(coupled_to) = coupled_to {
+                println!("files most coupled to {} (support, confidence):", coupled_to);
+                for (coupled_file, support, confidence) in co_change.get_most_coupled(&coupled_to, top) {
+                    println!("    {} ({}, {:.2})", coupled_file, support, confidence);
+                }
+            }
+
+            let json = serde_json::to_string_pretty(&co_change.get_graph(min_support, min_confidence)).unwrap();
+            let mut file = fs::File::create(new_filename.to_owned() + "_coupling.json").unwrap();
+            file.write_all(json.as_bytes()).unwrap();
+        }
+        ,
+        //printed before the config is read
+        Command::Completions { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bugfix::RegexClassifier;
+    use factors::default_factors;
+    use function_extractors::default_function_extractors;
+
+    //20 commits (age 0 is the newest) changing a.rs and b.rs, the ones with an age in bugfix_ages fix a.rs
+    fn get_raw_data(bugfix_ages: &[i32]) -> RawData {
+        let mut raw_data = RawData::new();
+        for age in 0..20 {
+            let is_bugfix = bugfix_ages.contains(&age);
+            let path = if is_bugfix || age % 2 == 0 { "a.rs" } else { "b.rs" };
+            raw_data.commits.insert(
+                format!("{:040}", age),
+                CommitRecord {
+                    message: if is_bugfix { "fix crash".to_string() } else { "add feature".to_string() },
+                    age,
+                    author_name: None,
+                    author_email: None,
+                    author_time: None,
+                    committer_time: None,
+                    parents: vec![],
+                    files: vec![FileChange {
+                        path: path.to_string(),
+                        lines_added: 1,
+                        loc: 10,
+                        ..Default::default()
+                    }],
+                    bug_introducing_commits: vec![],
+                },
+            );
+        }
+        raw_data
+    }
+
+    #[test]
+    fn horizon_shorter_than_the_gap_between_bugfixes() {
+        //the commit right after the 50% cuttof (age 10) is no bugfix, the one after the 70% cuttof (age 14) is
+        let raw_data = get_raw_data(&[15, 19]);
+        let grid = parse_evaluation_grid(&["cutoffs=50,70".to_string(), "horizon=1".to_string()], &[], &[10.0, 50.0]).unwrap();
+        let classifier = RegexClassifier::new(vec![Regex::new("fix").unwrap()]);
+        let age_options = AgeOptions {
+            mode: AgeMode::Position,
+            half_life: None,
+        };
+        let report = analyze_factors(
+            &raw_data,
+            &default_factors(),
+            &grid,
+            &CountingOptions {
+                bugfix_classifier: &classifier,
+                ground_truth: GroundTruth::Fixes,
+                filtered_filetypes: &[],
+                rename_map: &RenameMap::new(&raw_data),
+                age_options: &age_options,
+            },
+            &mut std::io::sink(),
+        );
+        assert_eq!(report.ranking.len(), report.factors.len());
+        for factor in &report.factors {
+            let without_bugs = &factor.cuttofs[0];
+            assert!(without_bugs.bugs_found.iter().all(|found| found.is_none()));
+            assert!(without_bugs.deviation.is_nan());
+            assert!(factor.cuttofs[1].bugs_found.iter().all(|found| found.is_some()));
+            assert!(!factor.average_deviation.is_nan());
+        }
+    }
+
+    #[test]
+    fn top_n_larger_than_the_file_count() {
+        let raw_data = get_raw_data(&[15, 19]);
+        let grid = parse_evaluation_grid(&["top_n=1,1000".to_string()], &[50], &[]).unwrap();
+        let classifier = RegexClassifier::new(vec![Regex::new("fix").unwrap()]);
+        let age_options = AgeOptions {
+            mode: AgeMode::Position,
+            half_life: None,
+        };
+        let report = analyze_factors(
+            &raw_data,
+            &default_factors(),
+            &grid,
+            &CountingOptions {
+                bugfix_classifier: &classifier,
+                ground_truth: GroundTruth::Fixes,
+                filtered_filetypes: &[],
+                rename_map: &RenameMap::new(&raw_data),
+                age_options: &age_options,
+            },
+            &mut std::io::sink(),
+        );
+        for factor in &report.factors {
+            assert!(!factor.average_bugs_found[0].is_nan());
+            assert!(factor.average_bugs_found[1].is_nan());
+            assert!(!factor.average_deviation.is_nan());
+        }
+    }
+
+    #[test]
+    fn hunk_lines_go_to_the_functions_around_them() {
+        let post_image = ["fn first() {", "    let a = 1;", "}", "", "fn second() {", "    let b = 2;", "    let c = 3;", "}"].join("\n");
+        let diff = [
+            "diff --git a/src/a.rs b/src/a.rs",
+            "index 1111111..2222222 100644",
+            "--- a/src/a.rs",
+            "+++ b/src/a.rs",
+            "@@ -1,7 +1,8 @@",
+            " fn first() {",
+            "-    let a = 0;",
+            "+    let a = 1;",
+            " }",
+            " ",
+            " fn second() {",
+            "     let b = 2;",
+            "+    let c = 3;",
+            " }",
+            "diff --git a/README.md b/README.md",
+            "--- a/README.md",
+            "+++ b/README.md",
+            "@@ -1 +1 @@",
+            "-old",
+            "+new",
+        ]
+        .join("\n");
+        let extractors = default_function_extractors();
+        //with the post-image the spans are used, without it the last signature seen
+        let post_images = HashMap::from([("src/a.rs".to_string(), post_image)]);
+        for post_images in [post_images, HashMap::new()] {
+            let files = get_functions_from_diff(&diff, &extractors, &post_images);
+            assert_eq!(files.len(), 2);
+            assert_eq!(files[0].path, "src/a.rs");
+            assert_eq!(files[0].functions, ["first", "second"]);
+            assert_eq!(files[0].function_churn["first"], (1, 1));
+            assert_eq!(files[0].function_churn["second"], (1, 0));
+            assert_eq!((files[0].lines_added, files[0].lines_deleted, files[0].hunks), (2, 1, 1));
+            assert!(files[1].functions.is_empty());
+            assert_eq!((files[1].lines_added, files[1].lines_deleted), (1, 1));
+        }
+    }
+
+    #[test]
+    fn coupled_files_share_their_bugs() {
+        //a.rs and b.rs change together, except for the bugfix at age 5 and the c.rs commits at ages 3 and 7
+        let mut raw_data = RawData::new();
+        for age in 0..10 {
+            let paths = match age {
+                3 | 7 => vec!["c.rs"],
+                5 => vec!["a.rs"],
+                _ => vec!["a.rs", "b.rs"],
+            };
+            raw_data.commits.insert(
+                format!("{:040}", age),
+                CommitRecord {
+                    message: if age == 1 || age == 5 { "fix crash".to_string() } else { "add feature".to_string() },
+                    age,
+                    author_name: None,
+                    author_email: None,
+                    author_time: None,
+                    committer_time: None,
+                    parents: vec![],
+                    files: paths.iter().map(|path| FileChange { path: path.to_string(), ..Default::default() }).collect(),
+                    bug_introducing_commits: vec![],
+                },
+            );
+        }
+        let classifier = RegexClassifier::new(vec![Regex::new("fix").unwrap()]);
+        let age_options = AgeOptions {
+            mode: AgeMode::Position,
+            half_life: None,
+        };
+        let file_list = file_data_map_to_file_list(
+            &raw_data,
+            EvaluationWindow { train_start: 0, cuttof: 100, test_end: 100, horizon: None },
+            &CountingOptions {
+                bugfix_classifier: &classifier,
+                ground_truth: GroundTruth::Fixes,
+                filtered_filetypes: &[],
+                rename_map: &RenameMap::new(&raw_data),
+                age_options: &age_options,
+            },
+        );
+        let [a, b, c] = ["a.rs", "b.rs", "c.rs"].map(|path| &file_list.files[path]);
+        assert!(a.bug_counter > b.bug_counter && b.bug_counter > 0.0);
+        assert_eq!((a.coupled_files, a.coupled_bug_counter), (1.0, b.bug_counter));
+        assert_eq!((b.coupled_files, b.coupled_bug_counter), (1.0, a.bug_counter));
+        assert_eq!((c.coupled_files, c.coupled_bug_counter), (0.0, 0.0));
+    }
+
+    #[test]
+    fn decay_starts_at_the_window() {
+        //a.rs changes at the even ages, the window counts ages 10 to 19 and the default half life is 2 commits
+        let raw_data = get_raw_data(&[]);
+        let classifier = RegexClassifier::new(vec![]);
+        let age_options = AgeOptions {
+            mode: AgeMode::Position,
+            half_life: None,
+        };
+        let counting = CountingOptions {
+            bugfix_classifier: &classifier,
+            ground_truth: GroundTruth::Fixes,
+            filtered_filetypes: &[],
+            rename_map: &RenameMap::new(&raw_data),
+            age_options: &age_options,
+        };
+        let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow { train_start: 50, cuttof: 100, test_end: 100, horizon: None }, &counting);
+        assert_eq!(file_list.files["a.rs"].decayed_freq_counter, 1.0 + 0.5 + 0.25 + 0.125 + 0.0625);
+        let file_list = file_data_map_to_file_list(&raw_data, EvaluationWindow { train_start: 0, cuttof: 100, test_end: 100, horizon: None }, &counting);
+        assert_eq!(file_list.files["a.rs"].decayed_freq_counter, (0..10).map(|i| 0.5_f32.powi(i)).sum::<f32>());
+    }
+
+    #[test]
+    fn all_parents_keeps_the_files_changed_against_every_parent() {
+        let change = |path: &str, functions: &[&str], lines_added: u32| FileChange {
+            path: path.to_string(),
+            functions: functions.iter().map(|function| function.to_string()).collect(),
+            lines_added,
+            ..Default::default()
+        };
+        //a.rs comes from the second parent and b.rs from the first, only c.rs was changed by the merge
+        let mut files = vec![change("a.rs", &["a"], 3), change("c.rs", &["c", "d"], 5)];
+        intersect_file_changes(&mut files, vec![change("b.rs", &["b"], 2), change("c.rs", &["c"], 2)]);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "c.rs");
+        assert_eq!(files[0].functions, vec!["c".to_string()]);
+        assert_eq!(files[0].lines_added, 2);
     }
 }
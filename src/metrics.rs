@@ -0,0 +1,196 @@
+use serde::Serialize;
+
+//Top % of the files that precision and recall are measured at
+pub const METRIC_TOP_PRECENTAGES: [usize; 2] = [10, 20];
+//% of the lines of code inspected for the effort aware recall
+pub const METRIC_EFFORT_PRECENTAGE: f64 = 20.0;
+
+//A file in a ranking, best first
+pub struct RankedFile {
+    pub score: f32,
+    //bugs fixed in it after the cuttof, a file with any is defective
+    pub bugs: f32,
+    //effort to inspect it, lines of code
+    pub loc: f32,
+}
+
+//How good a ranking is at one cuttof. Metrics that are undefined for it (like AUC without defective files) are NaN
+#[derive(Serialize, Clone)]
+pub struct Metrics {
+    //chance that a defective file is scored higher than a clean one, 0.5 is random
+    pub auc: f64,
+    //(top %, share of the files in it that are defective), the top is at least one file so small rankings have a precision too
+    pub precision_at_k: Vec<(usize, f64)>,
+    //(top %, share of the defective files that are in it)
+    pub recall_at_k: Vec<(usize, f64)>,
+    //effort aware, 1 - (optimal area - ranking area) / (optimal area - worst area) under the bugs found / lines inspected curve
+    pub popt: f64,
+    //share of the bugs found when inspecting METRIC_EFFORT_PRECENTAGE % of the lines
+    pub effort_recall: f64,
+    //area under the bugs found / files inspected curve, 0.5 is random
+    pub lift_area: f64,
+}
+
+impl Metrics {
+    pub fn new(ranked_files: &[RankedFile]) -> Metrics {
+        let defective_files = ranked_files.iter().filter(|file| file.bugs > 0.0).count() as f64;
+        let mut precision_at_k = vec![];
+        let mut recall_at_k = vec![];
+        for precentage in METRIC_TOP_PRECENTAGES {
+            //10% of less than 10 files would be no files and 0 / 0, only an empty ranking has no top file
+            let top_files = (ranked_files.len() * precentage / 100).max(1).min(ranked_files.len());
+            let found = ranked_files.iter().take(top_files).filter(|file| file.bugs > 0.0).count() as f64;
+            precision_at_k.push((precentage, found / top_files as f64));
+            recall_at_k.push((precentage, found / defective_files));
+        }
+
+        let total_loc: f64 = ranked_files.iter().map(|file| file.loc as f64).sum();
+        let total_bugs: f64 = ranked_files.iter().map(|file| file.bugs as f64).sum();
+        let mut inspected_loc = 0.0;
+        let mut effort_bugs = 0.0;
+        for file in ranked_files {
+            inspected_loc += file.loc as f64;
+            if inspected_loc / total_loc * 100.0 > METRIC_EFFORT_PRECENTAGE {
+                break;
+            }
+            effort_bugs += file.bugs as f64;
+        }
+
+        let mut optimal: Vec<&RankedFile> = ranked_files.iter().collect();
+        optimal.sort_by(|a, b| (b.bugs / b.loc).total_cmp(&(a.bugs / a.loc)).then(b.bugs.total_cmp(&a.bugs)));
+        let mut worst = optimal.clone();
+        worst.reverse();
+        let optimal_area = get_effort_area(&optimal);
+        let worst_area = get_effort_area(&worst);
+        let ranked_area = get_effort_area(&ranked_files.iter().collect::<Vec<&RankedFile>>());
+
+        Metrics {
+            auc: get_auc(ranked_files),
+            precision_at_k,
+            recall_at_k,
+            popt: 1.0 - (optimal_area - ranked_area) / (optimal_area - worst_area),
+            effort_recall: effort_bugs / total_bugs,
+            lift_area: get_area(ranked_files.iter().map(|file| (1.0, file.bugs as f64))),
+        }
+    }
+
+    //Average over several cuttofs, cuttofs where a metric is NaN are left out of its average
+    pub fn average(metrics: &[Metrics]) -> Metrics {
+        let mean = |values: Vec<f64>| {
+            let defined: Vec<f64> = values.into_iter().filter(|value| !value.is_nan()).collect();
+            defined.iter().sum::<f64>() / defined.len() as f64
+        };
+        let mean_at_k = |get: &dyn Fn(&Metrics) -> &Vec<(usize, f64)>| -> Vec<(usize, f64)> {
+            METRIC_TOP_PRECENTAGES
+                .iter()
+                .enumerate()
+                .map(|(i, precentage)| (*precentage, mean(metrics.iter().map(|m| get(m)[i].1).collect())))
+                .collect()
+        };
+        Metrics {
+            auc: mean(metrics.iter().map(|m| m.auc).collect()),
+            precision_at_k: mean_at_k(&|m| &m.precision_at_k),
+            recall_at_k: mean_at_k(&|m| &m.recall_at_k),
+            popt: mean(metrics.iter().map(|m| m.popt).collect()),
+            effort_recall: mean(metrics.iter().map(|m| m.effort_recall).collect()),
+            lift_area: mean(metrics.iter().map(|m| m.lift_area).collect()),
+        }
+    }
+}
+
+//Mann-Whitney U over the scores, tied scores get their average rank
+fn get_auc(ranked_files: &[RankedFile]) -> f64 {
+    let mut scores: Vec<(f32, bool)> = ranked_files.iter().map(|file| (file.score, file.bugs > 0.0)).collect();
+    scores.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let positives = scores.iter().filter(|(_, defective)| *defective).count() as f64;
+    let negatives = scores.len() as f64 - positives;
+    let mut positive_rank_sum = 0.0;
+    let mut start = 0;
+    while start < scores.len() {
+        let mut end = start;
+        while end < scores.len() && scores[end].0 == scores[start].0 {
+            end += 1;
+        }
+        let average_rank = (start + end + 1) as f64 / 2.0;
+        positive_rank_sum += average_rank * scores[start..end].iter().filter(|(_, defective)| *defective).count() as f64;
+        start = end;
+    }
+    (positive_rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives)
+}
+
+fn get_effort_area(files: &[&RankedFile]) -> f64 {
+    get_area(files.iter().map(|file| (file.loc as f64, file.bugs as f64)))
+}
+
+//Area under the curve of cumulative y share over cumulative x share, steps are (x, y) and the curve is linear within a step
+fn get_area(steps: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let total_x: f64 = steps.clone().map(|(x, _)| x).sum();
+    let total_y: f64 = steps.clone().map(|(_, y)| y).sum();
+    let mut area = 0.0;
+    let mut y = 0.0;
+    for (step_x, step_y) in steps {
+        area += step_x / total_x * (y + step_y / total_y / 2.0);
+        y += step_y / total_y;
+    }
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //(loc, bugs) in ranking order, the scores go down
+    fn get_ranking(files: &[(f32, f32)]) -> Vec<RankedFile> {
+        files
+            .iter()
+            .enumerate()
+            .map(|(i, (loc, bugs))| RankedFile { score: (files.len() - i) as f32, bugs: *bugs, loc: *loc })
+            .collect()
+    }
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn auc_with_ties() {
+        //defective 4 beats both clean files, defective 3 ties clean 3 (half) and beats clean 1: 3.5 of 4 pairs
+        let scores = [(4.0, 1.0), (3.0, 0.0), (3.0, 1.0), (1.0, 0.0)];
+        let ranked_files: Vec<RankedFile> = scores.iter().map(|(score, bugs)| RankedFile { score: *score, bugs: *bugs, loc: 1.0 }).collect();
+        assert_close(get_auc(&ranked_files), 0.875);
+        assert_close(get_auc(&get_ranking(&[(1.0, 1.0), (1.0, 0.0)])), 1.0);
+        assert_close(get_auc(&get_ranking(&[(1.0, 0.0), (1.0, 1.0)])), 0.0);
+        assert!(get_auc(&get_ranking(&[(1.0, 1.0), (1.0, 1.0)])).is_nan());
+    }
+
+    #[test]
+    fn popt_and_lift_area() {
+        //optimal is a, b, c (bugs per line) with area 0.85, worst c, b, a with 0.15 and this ranking b, a, c has 0.75
+        let a = (10.0, 1.0);
+        let b = (30.0, 1.0);
+        let c = (60.0, 0.0);
+        let metrics = Metrics::new(&get_ranking(&[b, a, c]));
+        assert_close(metrics.popt, 1.0 - 0.1 / 0.7);
+        //a third of the files each, 1/12 + 3/12 + 4/12
+        assert_close(metrics.lift_area, 8.0 / 12.0);
+        assert_close(Metrics::new(&get_ranking(&[a, b, c])).popt, 1.0);
+        assert_close(Metrics::new(&get_ranking(&[c, b, a])).popt, 0.0);
+    }
+
+    #[test]
+    fn precision_and_recall_at_k() {
+        let mut files = vec![(1.0, 1.0), (1.0, 0.0)];
+        files.extend([(1.0, 0.0); 7]);
+        files.push((1.0, 2.0));
+        let metrics = Metrics::new(&get_ranking(&files));
+        assert_eq!(metrics.precision_at_k, vec![(10, 1.0), (20, 0.5)]);
+        assert_eq!(metrics.recall_at_k, vec![(10, 0.5), (20, 0.5)]);
+
+        //10% of 5 files is the top file
+        let metrics = Metrics::new(&get_ranking(&[(1.0, 0.0), (1.0, 1.0), (1.0, 0.0), (1.0, 0.0), (1.0, 0.0)]));
+        assert_eq!(metrics.precision_at_k, vec![(10, 0.0), (20, 0.0)]);
+        let metrics = Metrics::new(&get_ranking(&[(1.0, 1.0), (1.0, 0.0)]));
+        assert_eq!(metrics.precision_at_k, vec![(10, 1.0), (20, 1.0)]);
+        assert!(Metrics::new(&[]).precision_at_k.iter().all(|(_, precision)| precision.is_nan()));
+    }
+}
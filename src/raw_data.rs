@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write as _;
+
+//Version written by write_raw_data. Version 1 is the old tuple format, which has no version field
+pub const RAW_DATA_FORMAT_VERSION: u32 = 2;
+
+//Everything "repo" extracts from a repository, what all the other modes read
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RawData {
+    pub format_version: u32,
+    //sha -> commit
+    pub commits: HashMap<String, CommitRecord>,
+    //true when "szz" found the bug introducing commits of the bugfixes
+    #[serde(default)]
+    pub bug_introducing: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommitRecord {
+    //the whole message, body and footer included
+    pub message: String,
+    //0 is the newest commit. -1 for commits without files in version 1 data, their age was never saved
+    pub age: i32,
+    #[serde(default)]
+    pub author_name: Option<String>,
+    #[serde(default)]
+    pub author_email: Option<String>,
+    //unix seconds
+    #[serde(default)]
+    pub author_time: Option<i64>,
+    #[serde(default)]
+    pub committer_time: Option<i64>,
+    #[serde(default)]
+    pub parents: Vec<String>,
+    pub files: Vec<FileChange>,
+    //for bugfixes, the commits that last changed the lines it fixed (see szz)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bug_introducing_commits: Vec<String>,
+}
+
+//A file changed in a commit
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FileChange {
+    pub path: String,
+    //path before the commit when the file was renamed in it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub functions: Vec<String>,
+    #[serde(default)]
+    pub lines_added: u32,
+    #[serde(default)]
+    pub lines_deleted: u32,
+    #[serde(default)]
+    pub hunks: u32,
+    //lines in the file after the commit, 0 when it was deleted or is binary
+    #[serde(default)]
+    pub loc: u32,
+    //function name -> (lines added, lines deleted) in it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub function_churn: HashMap<String, (u32, u32)>,
+    //bugfix commits that changed lines this change added, so it introduced their bugs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixed_by: Vec<String>,
+    //function name -> the bugfix commits of fixed_by that changed lines in it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub function_fixed_by: HashMap<String, Vec<String>>,
+}
+
+impl RawData {
+    pub fn new() -> RawData {
+        RawData {
+            format_version: RAW_DATA_FORMAT_VERSION,
+            commits: HashMap::new(),
+            bug_introducing: false,
+        }
+    }
+}
+
+//sha -> [(file, functions, age, message)]
+type Version1Data = HashMap<String, Vec<(String, Vec<String>, i32, String)>>;
+
+impl CommitRecord {
+    //What identifies the author when counting authors, the email if there is one since names are spelled differently more often
+    pub fn get_author_identity(&self) -> Option<String> {
+        match (&self.author_email, &self.author_name) {
+            (Some(email), _) if !email.is_empty() => Some(email.to_lowercase()),
+            (_, Some(name)) if !name.is_empty() => Some(name.to_owned()),
+            _ => None,
+        }
+    }
+
+    //The first line of the message, what `git log --pretty=oneline` shows. Older raw data only has this line
+    pub fn get_summary(&self) -> &str {
+        self.message.lines().next().unwrap_or("")
+    }
+}
+
+//Reads raw data in the current format, or converts version 1 data
+pub fn read_raw_data(json_path: &str) -> RawData {
+    let file_string = fs::read_to_string(json_path).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&file_string).unwrap();
+    match value.get("format_version").and_then(|version| version.as_u64()) {
+        Some(version) if version as u32 <= RAW_DATA_FORMAT_VERSION => serde_json::from_value(value).unwrap(),
+        Some(version) => panic!(
+            "{} has raw data format version {}, this program only reads up to version {}",
+            json_path, version, RAW_DATA_FORMAT_VERSION
+        ),
+        None => {
+            let old_data: Version1Data = serde_json::from_value(value).unwrap();
+            convert_version_1(old_data)
+        }
+    }
+}
+
+//Version 1 has no renames, authors or commit times, they stay empty
+fn convert_version_1(old_data: Version1Data) -> RawData {
+    let mut raw_data = RawData::new();
+    for (sha, files) in old_data {
+        let (age, message) = match files.first() {
+            Some(file) => (file.2, file.3.to_owned()),
+            None => (-1, String::new()),
+        };
+        let files = files
+            .into_iter()
+            .map(|(path, functions, _, _)| FileChange {
+                path,
+                functions,
+                ..Default::default()
+            })
+            .collect();
+        raw_data.commits.insert(
+            sha,
+            CommitRecord {
+                message,
+                age,
+                author_name: None,
+                author_email: None,
+                author_time: None,
+                committer_time: None,
+                parents: vec![],
+                files,
+                bug_introducing_commits: vec![],
+            },
+        );
+    }
+    raw_data
+}
+
+pub fn write_raw_data(json_path: &str, raw_data: &RawData) {
+    let json = serde_json::to_string_pretty(raw_data).unwrap();
+    let mut file = fs::File::create(json_path).unwrap();
+    file.write_all(json.as_bytes()).unwrap();
+}
+
+//Follows renames so every change of a file is counted under the name it has in the newest commit.
+//Uses the ages the raw data has when the map is made, so it keeps working if the ages are changed to days afterwards.
+pub struct RenameMap {
+    //old path -> (age of the renaming commit, new path)
+    renames: HashMap<String, Vec<(i32, String)>>,
+    //sha -> age
+    commit_ages: HashMap<String, i32>,
+}
+
+impl RenameMap {
+    pub fn new(raw_data: &RawData) -> RenameMap {
+        let mut renames: HashMap<String, Vec<(i32, String)>> = HashMap::new();
+        let mut commit_ages: HashMap<String, i32> = HashMap::new();
+        for (sha, commit) in &raw_data.commits {
+            commit_ages.insert(sha.to_owned(), commit.age);
+            for file in &commit.files {
+                if let Some(old_path) = &file.old_path {
+                    renames
+                        .entry(old_path.to_owned())
+                        .or_default()
+                        .push((commit.age, file.path.to_owned()));
+                }
+            }
+        }
+        RenameMap {
+            renames,
+            commit_ages,
+        }
+    }
+
+    //Lower age is newer, so a change made in commit sha is renamed by every later rename of its path with a lower age
+    pub fn get_current_name(&self, filename: &str, sha: &str) -> String {
+        let mut name = filename.to_string();
+        let mut age = match self.commit_ages.get(sha) {
+            Some(age) => *age,
+            None => return name,
+        };
+        loop {
+            let next_rename = self.renames.get(&name).and_then(|renames| {
+                renames
+                    .iter()
+                    .filter(|(rename_age, _)| *rename_age < age)
+                    .max_by_key(|(rename_age, _)| *rename_age)
+            });
+            match next_rename {
+                Some((rename_age, new_name)) => {
+                    name = new_name.to_owned();
+                    age = *rename_age;
+                }
+                None => return name,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_data_is_converted() {
+        let path = std::env::temp_dir().join(format!("gitdiffjson_version_1_{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "aaa": [["src/a.js", ["foo", "bar"], 3, "fix crash"], ["src/b.js", [], 3, "fix crash"]],
+                "bbb": []
+            }"#,
+        )
+        .unwrap();
+        let raw_data = read_raw_data(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(raw_data.format_version, RAW_DATA_FORMAT_VERSION);
+        assert!(!raw_data.bug_introducing);
+        let commit = &raw_data.commits["aaa"];
+        assert_eq!((commit.message.as_str(), commit.age), ("fix crash", 3));
+        assert_eq!(commit.author_name, None);
+        assert_eq!(commit.author_time, None);
+        assert!(commit.parents.is_empty());
+        assert_eq!(commit.files.len(), 2);
+        assert_eq!(commit.files[0].path, "src/a.js");
+        assert_eq!(commit.files[0].functions, ["foo", "bar"]);
+        assert_eq!(commit.files[0].old_path, None);
+        assert_eq!((commit.files[0].lines_added, commit.files[0].loc), (0, 0));
+        assert_eq!(commit.files[1].path, "src/b.js");
+        //the age of a commit without files was never saved
+        let commit = &raw_data.commits["bbb"];
+        assert_eq!((commit.message.as_str(), commit.age), ("", -1));
+        assert!(commit.files.is_empty());
+    }
+
+    //(age, path, old path) commits changing one file, named by their age
+    fn get_raw_data(commits: &[(i32, &str, Option<&str>)]) -> RawData {
+        let mut raw_data = RawData::new();
+        for (age, path, old_path) in commits {
+            raw_data.commits.insert(
+                format!("sha{}", age),
+                CommitRecord {
+                    message: String::new(),
+                    age: *age,
+                    author_name: None,
+                    author_email: None,
+                    author_time: None,
+                    committer_time: None,
+                    parents: vec![],
+                    files: vec![FileChange {
+                        path: path.to_string(),
+                        old_path: old_path.map(|old_path| old_path.to_string()),
+                        ..Default::default()
+                    }],
+                    bug_introducing_commits: vec![],
+                },
+            );
+        }
+        raw_data
+    }
+
+    #[test]
+    fn renames_are_followed_to_the_newest_name() {
+        //a.rs -> b.rs at age 5, b.rs -> c.rs at age 2, a new a.rs at age 1
+        let raw_data = get_raw_data(&[
+            (8, "a.rs", None),
+            (5, "b.rs", Some("a.rs")),
+            (3, "b.rs", None),
+            (2, "c.rs", Some("b.rs")),
+            (1, "a.rs", None),
+        ]);
+        let rename_map = RenameMap::new(&raw_data);
+        assert_eq!(rename_map.get_current_name("a.rs", "sha8"), "c.rs");
+        assert_eq!(rename_map.get_current_name("b.rs", "sha5"), "c.rs");
+        assert_eq!(rename_map.get_current_name("b.rs", "sha3"), "c.rs");
+        assert_eq!(rename_map.get_current_name("c.rs", "sha2"), "c.rs");
+        //the a.rs made after the rename is another file
+        assert_eq!(rename_map.get_current_name("a.rs", "sha1"), "a.rs");
+        assert_eq!(rename_map.get_current_name("a.rs", "unknown"), "a.rs");
+    }
+
+    #[test]
+    fn renames_back_to_an_earlier_name() {
+        //a.rs -> b.rs at age 6, b.rs -> a.rs again at age 3
+        let raw_data = get_raw_data(&[
+            (9, "a.rs", None),
+            (6, "b.rs", Some("a.rs")),
+            (4, "b.rs", None),
+            (3, "a.rs", Some("b.rs")),
+            (0, "a.rs", None),
+        ]);
+        let rename_map = RenameMap::new(&raw_data);
+        assert_eq!(rename_map.get_current_name("a.rs", "sha9"), "a.rs");
+        assert_eq!(rename_map.get_current_name("b.rs", "sha6"), "a.rs");
+        assert_eq!(rename_map.get_current_name("b.rs", "sha4"), "a.rs");
+        assert_eq!(rename_map.get_current_name("a.rs", "sha3"), "a.rs");
+        assert_eq!(rename_map.get_current_name("a.rs", "sha0"), "a.rs");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::grid::{Breakpoint, EvaluationWindow};
+use crate::metrics::{Metrics, METRIC_EFFORT_PRECENTAGE};
+use crate::stats::{get_statistic_values, FactorStatistics};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+//What "multi_analysis" and "text" write their results as
+#[derive(PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+//Parses "format=text|json|csv", other args are ignored
+pub fn parse_output_format(args: &[String]) -> Result<OutputFormat, String> {
+    let mut format = OutputFormat::Text;
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("format", "text")) => format = OutputFormat::Text,
+            Some(("format", "json")) => format = OutputFormat::Json,
+            Some(("format", "csv")) => format = OutputFormat::Csv,
+            Some(("format", value)) => return Err(format!("unknown format {}, use text, json or csv", value)),
+            _ => {}
+        }
+    }
+    Ok(format)
+}
+
+//Everything "multi_analysis" measured. Values are % of the bugs fixed after the cuttof, NaN (null in json) when there were none
+#[derive(Serialize)]
+pub struct MultiAnalysisReport {
+    pub windows: Vec<EvaluationWindow>,
+    //top % (or number) of the files the bugs found are counted at
+    pub breakpoints: Vec<Breakpoint>,
+    //factor slugs, best average deviation first
+    pub ranking: Vec<String>,
+    pub factors: Vec<FactorAnalysis>,
+}
+
+#[derive(Serialize)]
+pub struct FactorAnalysis {
+    pub slug: String,
+    pub name: String,
+    //1 is the best
+    pub rank: usize,
+    //average of (bugs found - % of the files looked at) over the breakpoints
+    pub average_deviation: f64,
+    //per breakpoint, averaged over the cuttofs that reached it, NaN when none did
+    pub average_bugs_found: Vec<f64>,
+    pub average_metrics: Metrics,
+    //significance of its place in the ranking, with the cuttofs as observations
+    pub statistics: FactorStatistics,
+    pub cuttofs: Vec<CuttofAnalysis>,
+}
+
+#[derive(Serialize)]
+pub struct CuttofAnalysis {
+    pub window: EvaluationWindow,
+    //per breakpoint, None when the cuttof has too few files to reach it or no bugs were fixed after it
+    pub bugs_found: Vec<Option<f32>>,
+    //average of (bugs found - % of the files looked at) over the breakpoints this cuttof reached, NaN without any
+    pub deviation: f64,
+    pub metrics: Metrics,
+}
+
+//Bugs found at the breakpoints of one cuttof, what "text" measures
+#[derive(Serialize)]
+pub struct TextReport {
+    pub cuttof: usize,
+    pub breakpoints: Vec<Breakpoint>,
+    pub factors: Vec<FactorBreakpoints>,
+}
+
+#[derive(Serialize)]
+pub struct FactorBreakpoints {
+    pub slug: String,
+    pub name: String,
+    //per breakpoint, None when there are too few files to reach it
+    pub bugs_found: Vec<Option<f32>>,
+}
+
+//Quotes a csv field when it has to be
+pub fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+//"top_10%" or "top_50_files"
+fn get_breakpoint_measure(breakpoint: &Breakpoint) -> String {
+    match breakpoint {
+        Breakpoint::Precentage(precentage) => format!("top_{}%", precentage),
+        Breakpoint::Count(count) => format!("top_{}_files", count),
+    }
+}
+
+//"name", "value" pairs of the metrics, for the flat csv
+fn get_metric_values(metrics: &Metrics) -> Vec<(String, f64)> {
+    let mut values = vec![("auc".to_string(), metrics.auc)];
+    for (k, precision) in &metrics.precision_at_k {
+        values.push((format!("precision@{}%", k), *precision));
+    }
+    for (k, recall) in &metrics.recall_at_k {
+        values.push((format!("recall@{}%", k), *recall));
+    }
+    values.push(("popt".to_string(), metrics.popt));
+    values.push((format!("recall@{}%loc", METRIC_EFFORT_PRECENTAGE), metrics.effort_recall));
+    values.push(("lift_area".to_string(), metrics.lift_area));
+    values
+}
+
+fn format_metrics(metrics: &Metrics) -> String {
+    let format_at_k = |values: &Vec<(usize, f64)>| {
+        values
+            .iter()
+            .map(|(k, value)| format!("{}%: {:.4}", k, value))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    format!(
+        "auc = {:.4}, precision@k = [{}], recall@k = [{}], popt = {:.4}, recall@{}%loc = {:.4}, lift area = {:.4}",
+        metrics.auc,
+        format_at_k(&metrics.precision_at_k),
+        format_at_k(&metrics.recall_at_k),
+        metrics.popt,
+        METRIC_EFFORT_PRECENTAGE,
+        metrics.effort_recall,
+        metrics.lift_area
+    )
+}
+
+impl MultiAnalysisReport {
+    //The ranking by average deviation, the metrics averaged over the cuttofs and per cuttof, then the average bugs found per breakpoint
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for slug in &self.ranking {
+            if let Some(factor) = self.factors.iter().find(|factor| factor.slug == *slug) {
+                let _ = writeln!(text, "{} > avg deviation = {}", factor.name, factor.average_deviation);
+            }
+        }
+        let _ = writeln!(text, "\nsignificance over the cuttofs (Scott-Knott tiers, bootstrap intervals, Wilcoxon signed-rank p-values)");
+        for slug in &self.ranking {
+            if let Some(factor) = self.factors.iter().find(|factor| factor.slug == *slug) {
+                let _ = writeln!(text, "{} > {}", factor.name, factor.statistics);
+            }
+        }
+        let _ = writeln!(text, "\nmetrics averaged over the cuttofs (NaN ones left out)");
+        for factor in &self.factors {
+            let _ = writeln!(text, "{} > {}", factor.name, format_metrics(&factor.average_metrics));
+        }
+        let _ = writeln!(text, "\nmetrics per cuttof");
+        for factor in &self.factors {
+            let _ = writeln!(text, "{}", factor.name);
+            for cuttof in &factor.cuttofs {
+                let _ = writeln!(text, "  {} => {}", cuttof.window, format_metrics(&cuttof.metrics));
+            }
+        }
+        let _ = writeln!(text);
+        for factor in &self.factors {
+            let _ = writeln!(text, "{}", factor.name);
+            for (breakpoint, found) in self.breakpoints.iter().zip(&factor.average_bugs_found) {
+                let _ = writeln!(text, "  {} => {}", breakpoint, found);
+            }
+        }
+        text
+    }
+
+    //One row per value: factor, name, rank, cuttof ("average" for the averages over the cuttofs, "0-40% -> 60%" for windows), measure, value
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("factor,name,rank,cuttof,measure,value\n");
+        for factor in &self.factors {
+            let mut add_row = |cuttof: &str, measure: &str, value: String| {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{}",
+                    csv_field(&factor.slug),
+                    csv_field(&factor.name),
+                    factor.rank,
+                    cuttof,
+                    measure,
+                    value
+                );
+            };
+            add_row("average", "deviation", factor.average_deviation.to_string());
+            for (breakpoint, found) in self.breakpoints.iter().zip(&factor.average_bugs_found) {
+                add_row("average", &get_breakpoint_measure(breakpoint), found.to_string());
+            }
+            for (measure, value) in get_metric_values(&factor.average_metrics) {
+                add_row("average", &measure, value.to_string());
+            }
+            for (measure, value) in get_statistic_values(&factor.statistics) {
+                add_row("average", measure, value.to_string());
+            }
+            for cuttof in &factor.cuttofs {
+                let cuttof_name = if cuttof.window.is_cuttof() {
+                    cuttof.window.cuttof.to_string()
+                } else {
+                    cuttof.window.to_string()
+                };
+                for (breakpoint, found) in self.breakpoints.iter().zip(&cuttof.bugs_found) {
+                    if let Some(found) = found {
+                        add_row(&cuttof_name, &get_breakpoint_measure(breakpoint), found.to_string());
+                    }
+                }
+                for (measure, value) in get_metric_values(&cuttof.metrics) {
+                    add_row(&cuttof_name, &measure, value.to_string());
+                }
+            }
+        }
+        csv
+    }
+}
+
+impl TextReport {
+    //One row per factor and breakpoint: factor, name, breakpoint, % of bugs found
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("factor,name,breakpoint,bugs_found\n");
+        for factor in &self.factors {
+            for (breakpoint, found) in self.breakpoints.iter().zip(&factor.bugs_found) {
+                if let Some(found) = found {
+                    let _ = writeln!(csv, "{},{},{},{}", csv_field(&factor.slug), csv_field(&factor.name), breakpoint, found);
+                }
+            }
+        }
+        csv
+    }
+}
@@ -0,0 +1,304 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::fmt;
+
+//p-value below which two factors (or tiers) count as different
+pub const SIGNIFICANCE: f64 = 0.05;
+pub const BOOTSTRAP_SAMPLES: usize = 1000;
+//fixed so the same data always gives the same intervals
+const BOOTSTRAP_SEED: u64 = 5;
+//up to this many pairs without tied differences the exact distribution is used, above it the normal approximation
+const EXACT_WILCOXON_MAX_PAIRS: usize = 25;
+
+//How sure we can be about a factors place in the ranking. The observations are its values per cuttof (or per repository)
+#[derive(Serialize, Clone)]
+pub struct FactorStatistics {
+    //1 is the best, factors in the same tier are not significantly different
+    pub tier: usize,
+    //95% bootstrap intervals of the mean
+    pub deviation_ci: (f64, f64),
+    pub auc_ci: (f64, f64),
+    //two sided Wilcoxon signed-rank p-values of the deviation against the next factor in the ranking and against the best one
+    pub p_vs_next: f64,
+    pub p_vs_best: f64,
+}
+
+impl fmt::Display for FactorStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tier = {}, deviation 95% ci = [{:.4}, {:.4}], auc 95% ci = [{:.4}, {:.4}], p vs next = {:.4}, p vs best = {:.4}",
+            self.tier,
+            self.deviation_ci.0,
+            self.deviation_ci.1,
+            self.auc_ci.0,
+            self.auc_ci.1,
+            self.p_vs_next,
+            self.p_vs_best
+        )
+    }
+}
+
+//"name", "value" pairs for the flat csv outputs
+pub fn get_statistic_values(statistics: &FactorStatistics) -> Vec<(&'static str, f64)> {
+    vec![
+        ("tier", statistics.tier as f64),
+        ("deviation_ci_low", statistics.deviation_ci.0),
+        ("deviation_ci_high", statistics.deviation_ci.1),
+        ("auc_ci_low", statistics.auc_ci.0),
+        ("auc_ci_high", statistics.auc_ci.1),
+        ("p_vs_next", statistics.p_vs_next),
+        ("p_vs_best", statistics.p_vs_best),
+    ]
+}
+
+//Two sided p-value of the Wilcoxon signed-rank test on the pairs (a[i], b[i]). Pairs with a NaN or no difference are left out, NaN without pairs
+pub fn wilcoxon_signed_rank(a: &[f64], b: &[f64]) -> f64 {
+    let mut differences: Vec<f64> = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| a - b)
+        .filter(|difference| !difference.is_nan() && *difference != 0.0)
+        .collect();
+    let n = differences.len();
+    if n == 0 {
+        return f64::NAN;
+    }
+    differences.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    //ranks of the absolute differences, tied ones get their average rank
+    let mut positive_rank_sum = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < n {
+        let mut end = start;
+        while end < n && differences[end].abs() == differences[start].abs() {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        positive_rank_sum += rank * differences[start..end].iter().filter(|difference| **difference > 0.0).count() as f64;
+        let ties = (end - start) as f64;
+        tie_correction += ties * ties * ties - ties;
+        start = end;
+    }
+
+    let n_f = n as f64;
+    let expected = n_f * (n_f + 1.0) / 4.0;
+    if tie_correction == 0.0 && n <= EXACT_WILCOXON_MAX_PAIRS {
+        //ways to get every positive rank sum when each rank is positive or negative with equal chance
+        let max_sum = n * (n + 1) / 2;
+        let mut ways = vec![0.0_f64; max_sum + 1];
+        ways[0] = 1.0;
+        for rank in 1..=n {
+            for sum in (rank..=max_sum).rev() {
+                ways[sum] += ways[sum - rank];
+            }
+        }
+        let total = 2.0_f64.powi(n as i32);
+        let w = positive_rank_sum.round() as usize;
+        let lower: f64 = ways[..=w].iter().sum::<f64>() / total;
+        let upper: f64 = ways[w..].iter().sum::<f64>() / total;
+        return (2.0 * lower.min(upper)).min(1.0);
+    }
+    let deviation = (n_f * (n_f + 1.0) * (2.0 * n_f + 1.0) / 24.0 - tie_correction / 48.0).sqrt();
+    //continuity correction
+    let z = ((positive_rank_sum - expected).abs() - 0.5).max(0.0) / deviation;
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+//Abramowitz and Stegun 7.1.26, error below 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - polynomial * (-x * x).exp())
+}
+
+//mean, median and variance leave NaN values out, they are NaN when nothing is left
+pub fn mean(values: &[f64]) -> f64 {
+    let defined: Vec<f64> = values.iter().cloned().filter(|value| !value.is_nan()).collect();
+    defined.iter().sum::<f64>() / defined.len() as f64
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    let mut defined: Vec<f64> = values.iter().cloned().filter(|value| !value.is_nan()).collect();
+    if defined.is_empty() {
+        return f64::NAN;
+    }
+    defined.sort_by(|a, b| a.total_cmp(b));
+    //the same element twice for odd lengths
+    (defined[(defined.len() - 1) / 2] + defined[defined.len() / 2]) / 2.0
+}
+
+//sample variance, NaN for less than two values
+pub fn variance(values: &[f64]) -> f64 {
+    let defined: Vec<f64> = values.iter().cloned().filter(|value| !value.is_nan()).collect();
+    let mean = mean(&defined);
+    defined.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (defined.len() as f64 - 1.0)
+}
+
+//95% percentile bootstrap interval of the mean, NaN values are left out
+pub fn bootstrap_mean_ci(values: &[f64]) -> (f64, f64) {
+    let defined: Vec<f64> = values.iter().cloned().filter(|value| !value.is_nan()).collect();
+    if defined.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    let mut rng = StdRng::seed_from_u64(BOOTSTRAP_SEED);
+    let mut means: Vec<f64> = (0..BOOTSTRAP_SAMPLES)
+        .map(|_| {
+            let sum: f64 = (0..defined.len()).map(|_| defined[rng.gen_range(0..defined.len())]).sum();
+            sum / defined.len() as f64
+        })
+        .collect();
+    means.sort_by(|a, b| a.total_cmp(b));
+    let low = means[(BOOTSTRAP_SAMPLES as f64 * 0.025) as usize];
+    let high = means[(BOOTSTRAP_SAMPLES as f64 * 0.975) as usize - 1];
+    (low, high)
+}
+
+//Scott-Knott style tiers. Factors (observations[i] are the values of factor i, higher is better, paired by position)
+//are sorted by mean and split where the means of the two parts differ the most, as long as the per observation
+//means of the two parts are significantly different by the Wilcoxon test. Returns the tier of every factor, 1 is the best.
+pub fn scott_knott(observations: &[Vec<f64>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..observations.len()).collect();
+    order.sort_by(|a, b| mean(&observations[*b]).total_cmp(&mean(&observations[*a])));
+    let mut groups: Vec<Vec<usize>> = vec![];
+    split_group(observations, &order, &mut groups);
+    let mut tiers = vec![0; observations.len()];
+    for (tier, group) in groups.iter().enumerate() {
+        for factor in group {
+            tiers[*factor] = tier + 1;
+        }
+    }
+    tiers
+}
+
+fn split_group(observations: &[Vec<f64>], group: &[usize], groups: &mut Vec<Vec<usize>>) {
+    if group.len() < 2 {
+        groups.push(group.to_vec());
+        return;
+    }
+    let means: Vec<f64> = group.iter().map(|factor| mean(&observations[*factor])).collect();
+    let total_mean = means.iter().sum::<f64>() / means.len() as f64;
+    //the split with the largest sum of squares between the two parts
+    let mut best_split = 1;
+    let mut best_between = f64::NEG_INFINITY;
+    for split in 1..group.len() {
+        let first_mean = means[..split].iter().sum::<f64>() / split as f64;
+        let second_mean = means[split..].iter().sum::<f64>() / (group.len() - split) as f64;
+        let between = split as f64 * (first_mean - total_mean).powi(2)
+            + (group.len() - split) as f64 * (second_mean - total_mean).powi(2);
+        if between > best_between {
+            best_between = between;
+            best_split = split;
+        }
+    }
+    let part_means = |part: &[usize]| -> Vec<f64> {
+        let observation_count = part.iter().map(|factor| observations[*factor].len()).max().unwrap_or(0);
+        (0..observation_count)
+            .map(|i| mean(&part.iter().filter_map(|factor| observations[*factor].get(i).cloned()).collect::<Vec<f64>>()))
+            .collect()
+    };
+    let p = wilcoxon_signed_rank(&part_means(&group[..best_split]), &part_means(&group[best_split..]));
+    if p < SIGNIFICANCE {
+        split_group(observations, &group[..best_split], groups);
+        split_group(observations, &group[best_split..], groups);
+    } else {
+        groups.push(group.to_vec());
+    }
+}
+
+//Statistics for every factor. deviations[i] and aucs[i] are the observations of factor i, ranking the factor indexes best first
+pub fn compare_factors(deviations: &[Vec<f64>], aucs: &[Vec<f64>], ranking: &[usize]) -> Vec<FactorStatistics> {
+    let tiers = scott_knott(deviations);
+    (0..deviations.len())
+        .map(|i| {
+            let place = ranking.iter().position(|factor| *factor == i).unwrap();
+            let p_vs_next = match ranking.get(place + 1) {
+                Some(next) => wilcoxon_signed_rank(&deviations[i], &deviations[*next]),
+                None => f64::NAN,
+            };
+            let p_vs_best = if place == 0 { f64::NAN } else { wilcoxon_signed_rank(&deviations[i], &deviations[ranking[0]]) };
+            FactorStatistics {
+                tier: tiers[i],
+                deviation_ci: bootstrap_mean_ci(&deviations[i]),
+                auc_ci: bootstrap_mean_ci(&aucs[i]),
+                p_vs_next,
+                p_vs_best,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64, tolerance: f64) {
+        assert!((value - expected).abs() < tolerance, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn wilcoxon_exact() {
+        let zeros = [0.0; 6];
+        //every difference positive: only 1 of the 2^5 sign patterns has a rank sum this high, two sided 2/32
+        assert_close(wilcoxon_signed_rank(&[1.0, 2.0, 3.0, 4.0, 5.0], &zeros[..5]), 2.0 / 32.0, 1e-12);
+        assert_close(wilcoxon_signed_rank(&zeros[..5], &[1.0, 2.0, 3.0, 4.0, 5.0]), 2.0 / 32.0, 1e-12);
+        //positive rank sum 11, 7 of the 32 sign patterns reach 11 or more
+        assert_close(wilcoxon_signed_rank(&[1.0, 2.0, 3.0, -4.0, 5.0], &zeros[..5]), 14.0 / 32.0, 1e-12);
+        //pairs without a difference or with a NaN are left out
+        assert_close(wilcoxon_signed_rank(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, f64::NAN], &[0.0, 0.0, 0.0, 0.0, 0.0, 6.0, 0.0]), 2.0 / 32.0, 1e-12);
+        assert!(wilcoxon_signed_rank(&[1.0, 2.0], &[1.0, 2.0]).is_nan());
+    }
+
+    #[test]
+    fn wilcoxon_normal_approximation() {
+        //30 pairs is above EXACT_WILCOXON_MAX_PAIRS, the multiples of 3 are negative so the positive rank sum is 300 of the expected 232.5
+        let differences: Vec<f64> = (1..=30).map(|i| if i % 3 == 0 { -(i as f64) } else { i as f64 }).collect();
+        assert_close(wilcoxon_signed_rank(&differences, &[0.0; 30]), 0.168179, 1e-5);
+        //tied differences use the approximation too, a rank sum at the expected value is p 1
+        assert_close(wilcoxon_signed_rank(&[1.0, -1.0, 1.0, -1.0], &[0.0; 4]), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn bootstrap_interval() {
+        assert_eq!(bootstrap_mean_ci(&[2.0, 2.0, 2.0, f64::NAN]), (2.0, 2.0));
+        let values: Vec<f64> = (1..=20).map(|i| i as f64).collect();
+        let (low, high) = bootstrap_mean_ci(&values);
+        assert!(low < 10.5 && 10.5 < high, "[{}, {}]", low, high);
+        assert!(low > 1.0 && high < 20.0, "[{}, {}]", low, high);
+        //the seed is fixed
+        assert_eq!(bootstrap_mean_ci(&values), (low, high));
+        let (low, high) = bootstrap_mean_ci(&[f64::NAN]);
+        assert!(low.is_nan() && high.is_nan());
+    }
+
+    #[test]
+    fn scott_knott_two_separated_groups() {
+        //two factors around 10.5 and two around 0.5, the pairs inside a group differ by +1 and -1 in turns
+        let observations: Vec<Vec<f64>> = [0.0, 10.0, 0.0, 10.0]
+            .iter()
+            .enumerate()
+            .map(|(factor, base)| (0..10).map(|i| base + ((i + factor / 2) % 2) as f64).collect())
+            .collect();
+        assert_eq!(scott_knott(&observations), vec![2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn mean_median_and_variance_leave_nan_out() {
+        let values = [4.0, f64::NAN, 1.0, 7.0, 2.0];
+        assert_eq!(mean(&values), 3.5);
+        assert_eq!(median(&values), 3.0);
+        assert_eq!(median(&[5.0, 1.0, 3.0]), 3.0);
+        assert_close(variance(&values), 7.0, 1e-12);
+        assert!(median(&[f64::NAN]).is_nan());
+        assert!(variance(&[1.0]).is_nan());
+        assert!(mean(&[]).is_nan());
+    }
+}
@@ -0,0 +1,370 @@
+use crate::bugfix::BugfixClassifier;
+use crate::function_extractors::{get_enclosing_function, get_extractor_for_file, FunctionExtractor};
+use crate::raw_data::RawData;
+use git2::{BlameOptions, DiffFindOptions, Oid, Patch, Repository, RepositoryOpenFlags};
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::Path;
+
+//Starts of lines that are only a comment, changing them can not have caused a bug
+const COMMENT_PREFIXES: [&str; 7] = ["//", "#", "/*", "*", "--", "<!--", ";"];
+
+//What the bugs that "multi_analysis" and the other modes measure against are
+#[derive(Clone, Copy, PartialEq)]
+pub enum GroundTruth {
+    //bugs fixed by the bugfix commits after the cuttof
+    Fixes,
+    //bugs introduced by the commits after the cuttof, changes whose lines a later bugfix changed (found by "szz")
+    Introduced,
+}
+
+//Parses "truth=fixes|introduced", other args are ignored
+pub fn parse_ground_truth(args: &[String]) -> Result<GroundTruth, String> {
+    let mut ground_truth = GroundTruth::Fixes;
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("truth", "fixes")) => ground_truth = GroundTruth::Fixes,
+            Some(("truth", "introduced")) => ground_truth = GroundTruth::Introduced,
+            Some(("truth", value)) => return Err(format!("unknown truth {}, use fixes or introduced", value)),
+            _ => {}
+        }
+    }
+    Ok(ground_truth)
+}
+
+//truth=introduced needs raw data that "szz" (or "repo" with szz=true) has run on
+pub fn check_ground_truth(ground_truth: GroundTruth, raw_data: &RawData) -> Result<(), String> {
+    if ground_truth == GroundTruth::Introduced && !raw_data.bug_introducing {
+        return Err("truth=introduced needs raw data with the bug introducing commits, run \"szz\" or \"repo\" with szz=true first".to_string());
+    }
+    Ok(())
+}
+
+//A change of path (and function) in commit sha that a bugfix blamed
+struct Introduction {
+    sha: String,
+    path: String,
+    function: Option<String>,
+}
+
+fn is_comment_or_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || COMMENT_PREFIXES.iter().any(|prefix| line.starts_with(prefix))
+}
+
+fn without_whitespace(line: &str) -> String {
+    line.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+//(path in the parent, lines) a bugfix deleted or modified in a file
+type FixedLines = (String, Vec<usize>);
+
+//Lines (1-based, in the parent) a bugfix deleted or modified in every file it changed, and the parent.
+//Blank and comment only lines are left out, and so are lines that only got their whitespace changed in the same hunk.
+fn get_fixed_lines(repo: &Repository, fix_sha: &str, filtered_filetypes: &[Regex]) -> Option<(Oid, Vec<FixedLines>)> {
+    let commit = repo.find_commit(Oid::from_str(fix_sha).ok()?).ok()?;
+    let parent = commit.parent(0).ok()?;
+    let mut diff = repo
+        .diff_tree_to_tree(Some(&parent.tree().ok()?), Some(&commit.tree().ok()?), None)
+        .ok()?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true))).ok()?;
+
+    let mut fixed_files = vec![];
+    for delta_index in 0..diff.deltas().len() {
+        let delta = diff.get_delta(delta_index)?;
+        let old_path = match delta.old_file().path() {
+            Some(path) if !delta.old_file().id().is_zero() => path.to_string_lossy().to_string(),
+            _ => continue,
+        };
+        if filtered_filetypes.iter().any(|regex| regex.is_match(&old_path)) {
+            continue;
+        }
+        let patch = match Patch::from_diff(&diff, delta_index) {
+            Ok(Some(patch)) => patch,
+            _ => continue,
+        };
+        let mut lines = vec![];
+        for hunk_index in 0..patch.num_hunks() {
+            let mut deleted = vec![];
+            let mut added = HashSet::new();
+            for line_index in 0..patch.num_lines_in_hunk(hunk_index).unwrap_or(0) {
+                let line = match patch.line_in_hunk(hunk_index, line_index) {
+                    Ok(line) => line,
+                    Err(_) => continue,
+                };
+                let content = String::from_utf8_lossy(line.content()).to_string();
+                match (line.origin(), line.old_lineno()) {
+                    ('-', Some(line_number)) => deleted.push((line_number as usize, content)),
+                    ('+', _) => {
+                        added.insert(without_whitespace(&content));
+                    }
+                    _ => {}
+                }
+            }
+            lines.extend(
+                deleted
+                    .into_iter()
+                    .filter(|(_, content)| !is_comment_or_blank(content) && !added.contains(&without_whitespace(content)))
+                    .map(|(line_number, _)| line_number),
+            );
+        }
+        if !lines.is_empty() {
+            fixed_files.push((old_path, lines));
+        }
+    }
+    Some((parent.id(), fixed_files))
+}
+
+//Blames the fixed lines in the parent of the bugfix, every (commit, file, function) that last changed one of them introduced the bug
+fn find_introductions(
+    repo: &Repository,
+    fix_sha: &str,
+    filtered_filetypes: &[Regex],
+    function_extractors: &[FunctionExtractor],
+) -> Vec<Introduction> {
+    let (parent_id, fixed_files) = match get_fixed_lines(repo, fix_sha, filtered_filetypes) {
+        Some(fixed) => fixed,
+        None => return vec![],
+    };
+    let parent_tree = repo.find_commit(parent_id).and_then(|parent| parent.tree()).ok();
+    let mut introductions: Vec<Introduction> = vec![];
+    for (path, lines) in fixed_files {
+        let mut options = BlameOptions::new();
+        options
+            .newest_commit(parent_id)
+            .min_line(*lines.iter().min().unwrap())
+            .max_line(*lines.iter().max().unwrap());
+        let blame = match repo.blame_file(Path::new(&path), Some(&mut options)) {
+            Ok(blame) => blame,
+            Err(_) => continue,
+        };
+        //functions are found in the parent, where the fixed lines are
+        let spans = get_extractor_for_file(function_extractors, &path).and_then(|extractor| {
+            let entry = parent_tree.as_ref()?.get_path(Path::new(&path)).ok()?;
+            let blob = repo.find_blob(entry.id()).ok()?;
+            Some(extractor.get_function_spans(&String::from_utf8_lossy(blob.content())))
+        });
+        for line_number in lines {
+            let hunk = match blame.get_line(line_number) {
+                Some(hunk) => hunk,
+                None => continue,
+            };
+            let introduction = Introduction {
+                sha: hunk.final_commit_id().to_string(),
+                path: hunk.path().map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|| path.clone()),
+                function: spans
+                    .as_ref()
+                    .and_then(|spans| get_enclosing_function(spans, line_number))
+                    .map(|function| function.to_string()),
+            };
+            if !introductions.iter().any(|other| {
+                other.sha == introduction.sha && other.path == introduction.path && other.function == introduction.function
+            }) {
+                introductions.push(introduction);
+            }
+        }
+    }
+    introductions
+}
+
+//SZZ: finds the commits that introduced the bugs fixed by every bugfix commit (by bugfix_classifier) and saves them in raw_data.
+//Fix commits get the introducing commits in bug_introducing_commits, the changed files of the introducing commits the fixes in fixed_by
+//(and per function in function_fixed_by). Earlier results are replaced. Introducing commits that are not in raw_data are left out.
+pub fn find_bug_introducing_commits(
+    repo_path: &str,
+    raw_data: &mut RawData,
+    bugfix_classifier: &dyn BugfixClassifier,
+    filtered_filetypes: &[Regex],
+    function_extractors: &[FunctionExtractor],
+) {
+    for commit in raw_data.commits.values_mut() {
+        commit.bug_introducing_commits.clear();
+        for file in &mut commit.files {
+            file.fixed_by.clear();
+            file.function_fixed_by.clear();
+        }
+    }
+    let fixes: Vec<String> = raw_data
+        .commits
+        .iter()
+        .filter(|(sha, commit)| bugfix_classifier.is_bugfix(sha, commit))
+        .map(|(sha, _)| sha.to_owned())
+        .collect();
+    eprintln!("finding the commits that introduced the bugs of {} bugfix commits", fixes.len());
+
+    let pb = ProgressBar::new(fixes.len().try_into().unwrap());
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40} {pos}/{len} [{elapsed_precise}] ({eta})")
+            .unwrap(),
+    );
+    let results: Vec<(String, Vec<Introduction>)> = fixes
+        .par_iter()
+        .map(|fix_sha| {
+            pb.inc(1);
+            let introductions = match Repository::open_ext(repo_path, RepositoryOpenFlags::empty(), Vec::<OsString>::new()) {
+                Ok(repo) => find_introductions(&repo, fix_sha, filtered_filetypes, function_extractors),
+                Err(err) => {
+                    eprintln!("Failed to open repository: {}", err);
+                    vec![]
+                }
+            };
+            (fix_sha.to_owned(), introductions)
+        })
+        .collect();
+    pb.finish();
+
+    let mut found = 0;
+    let mut missing = 0;
+    for (fix_sha, introductions) in results {
+        let mut introducing_commits: Vec<String> = vec![];
+        for introduction in introductions {
+            //the fix itself can not have introduced the bug, and neither can commits that were not parsed
+            let file = match raw_data.commits.get_mut(&introduction.sha) {
+                Some(commit) if introduction.sha != fix_sha => commit.files.iter_mut().find(|file| file.path == introduction.path),
+                _ => None,
+            };
+            let file = match file {
+                Some(file) => file,
+                None => {
+                    missing += 1;
+                    continue;
+                }
+            };
+            found += 1;
+            if !file.fixed_by.contains(&fix_sha) {
+                file.fixed_by.push(fix_sha.to_owned());
+            }
+            if let Some(function) = introduction.function {
+                let fixes = file.function_fixed_by.entry(function).or_default();
+                if !fixes.contains(&fix_sha) {
+                    fixes.push(fix_sha.to_owned());
+                }
+            }
+            if !introducing_commits.contains(&introduction.sha) {
+                introducing_commits.push(introduction.sha);
+            }
+        }
+        if let Some(fix) = raw_data.commits.get_mut(&fix_sha) {
+            fix.bug_introducing_commits = introducing_commits;
+        }
+    }
+    raw_data.bug_introducing = true;
+    eprintln!("{} blamed changes found, {} in commits or files that are not in the raw data", found, missing);
+}
+
+//Number of fixes in fixed_by that are at least min_age old, the ones that are known when only looking at commits up to min_age
+pub fn count_known_fixes(raw_data: &RawData, fixed_by: &[String], min_age: i32) -> f32 {
+    fixed_by
+        .iter()
+        .filter(|sha| raw_data.commits.get(*sha).is_some_and(|fix| fix.age >= min_age))
+        .count() as f32
+}
+
+//fixed_by per function with counts, for the function counters
+pub fn count_known_function_fixes(raw_data: &RawData, function_fixed_by: &HashMap<String, Vec<String>>, min_age: i32) -> Vec<(String, f32)> {
+    function_fixed_by
+        .iter()
+        .map(|(function, fixed_by)| (function.to_owned(), count_known_fixes(raw_data, fixed_by, min_age)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bugfix::RegexClassifier;
+    use crate::function_extractors::default_function_extractors;
+    use crate::raw_data::{CommitRecord, FileChange};
+    use git2::Signature;
+    use std::path::PathBuf;
+
+    //An empty repository in the temp folder, name keeps the tests apart
+    fn init_repo(name: &str) -> (PathBuf, Repository) {
+        let path = std::env::temp_dir().join(format!("gitdiffjson_szz_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).unwrap();
+        (path, repo)
+    }
+
+    //Writes a.rs with content and commits it on top of HEAD, returns the sha
+    fn commit(repo: &Repository, content: &str, message: &str) -> String {
+        std::fs::write(repo.workdir().unwrap().join("a.rs"), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("a", "a@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap().to_string()
+    }
+
+    fn get_commit_record(message: &str, age: i32, functions: &[&str]) -> CommitRecord {
+        CommitRecord {
+            message: message.to_string(),
+            age,
+            author_name: None,
+            author_email: None,
+            author_time: None,
+            committer_time: None,
+            parents: vec![],
+            files: vec![FileChange {
+                path: "a.rs".to_string(),
+                functions: functions.iter().map(|function| function.to_string()).collect(),
+                ..Default::default()
+            }],
+            bug_introducing_commits: vec![],
+        }
+    }
+
+    #[test]
+    fn fixed_lines_leave_out_comments_blanks_and_whitespace() {
+        let (path, repo) = init_repo("fixed_lines");
+        commit(&repo, "fn a() {\n    let x = 1;\n    // old comment\n    let y = 2;\n\n    let z = 3;\n}\n", "add a");
+        //line 2 is fixed, the comment and the blank line are deleted and line 4 only gets its whitespace changed
+        let fix = commit(&repo, "fn a() {\n    let x = 2;\n    let  y = 2;\n    let z = 3;\n}\n", "fix a");
+        let (_, fixed_files) = get_fixed_lines(&repo, &fix, &[]).unwrap();
+        assert_eq!(fixed_files, vec![("a.rs".to_string(), vec![2])]);
+        //filtered files are not blamed
+        let (_, fixed_files) = get_fixed_lines(&repo, &fix, &[Regex::new(r"\.rs$").unwrap()]).unwrap();
+        assert!(fixed_files.is_empty());
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn known_fixes_are_the_ones_old_enough() {
+        let mut raw_data = RawData::new();
+        raw_data.commits.insert("old".to_string(), get_commit_record("fix", 5, &[]));
+        raw_data.commits.insert("new".to_string(), get_commit_record("fix", 2, &[]));
+        let fixed_by = ["old".to_string(), "new".to_string(), "unknown".to_string()];
+        assert_eq!(count_known_fixes(&raw_data, &fixed_by, 0), 2.0);
+        assert_eq!(count_known_fixes(&raw_data, &fixed_by, 3), 1.0);
+        assert_eq!(count_known_fixes(&raw_data, &fixed_by, 6), 0.0);
+        let function_fixed_by = HashMap::from([("a".to_string(), fixed_by[..2].to_vec())]);
+        assert_eq!(count_known_function_fixes(&raw_data, &function_fixed_by, 3), vec![("a".to_string(), 1.0)]);
+    }
+
+    #[test]
+    fn bug_introducing_commits_are_found_by_blame() {
+        let (path, repo) = init_repo("introducing");
+        let add = commit(&repo, "fn a() {\n    let x = 1;\n}\n\nfn b() {\n    let y = 1;\n}\n", "add a and b");
+        let fix = commit(&repo, "fn a() {\n    let x = 2;\n}\n\nfn b() {\n    let y = 1;\n}\n", "fix a");
+        let mut raw_data = RawData::new();
+        raw_data.commits.insert(add.clone(), get_commit_record("add a and b", 1, &["a", "b"]));
+        raw_data.commits.insert(fix.clone(), get_commit_record("fix a", 0, &["a"]));
+        let classifier = RegexClassifier::new(vec![Regex::new("fix").unwrap()]);
+        find_bug_introducing_commits(path.to_str().unwrap(), &mut raw_data, &classifier, &[], &default_function_extractors());
+
+        assert!(raw_data.bug_introducing);
+        let introducing = &raw_data.commits[&add];
+        assert_eq!(introducing.files[0].fixed_by, vec![fix.clone()]);
+        assert_eq!(introducing.files[0].function_fixed_by, HashMap::from([("a".to_string(), vec![fix.clone()])]));
+        assert!(introducing.bug_introducing_commits.is_empty());
+        assert_eq!(raw_data.commits[&fix].bug_introducing_commits, vec![add]);
+        assert!(raw_data.commits[&fix].files[0].fixed_by.is_empty());
+        let _ = std::fs::remove_dir_all(path);
+    }
+}